@@ -0,0 +1,67 @@
+use chrono::{DateTime, NaiveDate, Utc, Duration as ChronoDuration};
+use std::fmt;
+
+/// A stream with a total budget, it stops once the budget is traded
+/// or the end date has passed
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    /// total amount to trade, in microns
+    pub total_amount: u64,
+    /// trades before this date do not count towards the budget
+    pub start_at: DateTime<Utc>,
+    pub end_at: Option<DateTime<Utc>>,
+    /// amount already traded, in microns
+    pub filled: u64,
+}
+
+impl Campaign {
+    pub fn remaining(&self) -> u64 {
+        self.total_amount.saturating_sub(self.filled)
+    }
+
+    /// Why the campaign is over, if it is
+    pub fn finish_reason(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        if self.remaining() == 0 {
+            Some("budget exhausted")
+        } else if self.end_at.is_some_and(|end_at| end_at <= now) {
+            Some("end date passed")
+        } else {
+            None
+        }
+    }
+
+    /// Daily amount for the day starting now: the remaining budget over the days
+    /// left until the end date, never above `max_daily_amount`
+    pub fn daily_rate(&self, max_daily_amount: u64, now: DateTime<Utc>) -> u64 {
+        let remaining = self.remaining();
+        let rate = match self.end_at {
+            Some(end_at) => {
+                // A started day counts as a full day
+                let days_left = ((end_at - now).num_seconds() as f64 / ChronoDuration::days(1).num_seconds() as f64).ceil().max(1.0);
+                (remaining as f64 / days_left).ceil() as u64
+            }
+            None => remaining,
+        };
+        rate.min(max_daily_amount)
+    }
+}
+
+impl fmt::Display for Campaign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} filled since {}", self.filled as f64 / 1_000_000.0, self.total_amount as f64 / 1_000_000.0, self.start_at.to_rfc3339())?;
+        if let Some(end_at) = self.end_at {
+            write!(f, ", ends {}", end_at.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an RFC 3339 date time, or a `YYYY-MM-DD` date at midnight UTC
+pub fn parse_datetime(input: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", input))
+}
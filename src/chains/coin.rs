@@ -54,6 +54,30 @@ impl Coin {
             // Add more cases as needed
         }
     }
+
+    // Number of decimals of the on-chain amounts
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Coin::WLibra | Coin::USDC | Coin::OSMO | Coin::TOSMO | Coin::TUSDC => 6,
+        }
+    }
+
+    // Method to get the coin of a denomination, uosmo maps to OSMO
+    pub fn from_denom(denom: &str) -> Option<Coin> {
+        match denom {
+            "factory/osmo19hdqma2mj0vnmgcxag6ytswjnr8a3y07q7e70p/wLIBRA" => Some(Coin::WLibra),
+            "ibc/498A0751C798A0D9A389AA3691123DADA57DAA4FE165D5C75894505B876BA6E4" => Some(Coin::USDC),
+            "uosmo" => Some(Coin::OSMO),
+            // TEST coins
+            "factory/osmo109ns4u04l44kqdkvp876hukd3hxz8zzm7809el/uusdc" => Some(Coin::TUSDC),
+            _ => None,
+        }
+    }
+}
+
+// Parses a coin name, for command line arguments
+pub fn parse_coin(input: &str) -> Result<Coin, String> {
+    input.parse().map_err(|_| format!("Unknown coin '{}'", input))
 }
 
 // Struct for holding coin balances
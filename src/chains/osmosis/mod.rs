@@ -1,4 +1,9 @@
 pub(crate) mod osmosis_pool_service;
 pub(crate) mod osmosis_account_service;
 pub(crate) mod osmosis_key_service;
-pub(crate) mod osmosis_transaction;
\ No newline at end of file
+pub(crate) mod osmosis_transaction;
+pub(crate) mod osmosis_endpoint_service;pub(crate) mod osmosis_cl_service;
+pub(crate) mod osmosis_lp_service;
+pub(crate) mod osmosis_tx_builder;
+pub(crate) mod osmosis_transfer_service;
+pub(crate) mod osmosis_reconcile_service;
@@ -1,8 +1,6 @@
 use serde::Deserialize;
 use std::error::Error as StdError;
-use reqwest;
-use reqwest::Client;
-use crate::config::CONFIG;
+use crate::chains::osmosis::osmosis_endpoint_service::{with_failover, EndpointRole, HTTP_CLIENT};
 use crate::chains::coin::{Coin, CoinAmount};
 use cosmrs::tx::SequenceNumber;
 
@@ -30,16 +28,10 @@ struct AccountResponse {
 }
 
 pub async fn fetch_account_info(address: &str) -> Result<(u64, SequenceNumber), Box<dyn StdError>> {
-    let client = Client::new();
-    let url = get_osmosis_account_info_url();
-    let formated_url = url.replace("{}", address);
-
-    let res = client
-        .get(&formated_url)
-        .send()
-        .await?
-        .json::<AccountResponse>()
-        .await?;
+    let (res, _) = with_failover(EndpointRole::AccountInfo, |url| {
+        let request = HTTP_CLIENT.get(url.replace("{}", address)).send();
+        async move { request.await?.error_for_status()?.json::<AccountResponse>().await }
+    }).await?;
 
     let account_number = res.base_account.account_number.parse::<u64>()?;
     let sequence = res.base_account.sequence.parse::<SequenceNumber>()?;
@@ -48,12 +40,9 @@ pub async fn fetch_account_info(address: &str) -> Result<(u64, SequenceNumber),
 }
 
 pub async fn fetch_balances(address: &str, coins: Option<Vec<Coin>>) -> Result<Vec<CoinAmount>, Box<dyn StdError>> {
-    let url = get_account_balance_url();
-    let url_formated = url.replace("{}", address);
-    let response = reqwest::get(&url_formated).await?;
-    let response = response.error_for_status()?;
-    
-    let balances: BalancesResponse = response.json().await?;
+    let (balances, _) = with_failover(EndpointRole::AccountBalances, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", address)).send().await?.error_for_status()?.json::<BalancesResponse>().await
+    }).await?;
     let mut result = Vec::new();
 
     match coins {
@@ -68,14 +57,9 @@ pub async fn fetch_balances(address: &str, coins: Option<Vec<Coin>>) -> Result<V
         None => {
             for balance in balances.balances {
                 let amount = balance.amount.parse()?;
-                let coin = match balance.denom.as_str() {
-                    "factory/osmo19hdqma2mj0vnmgcxag6ytswjnr8a3y07q7e70p/wLIBRA" => Coin::WLibra,
-                    "ibc/498A0751C798A0D9A389AA3691123DADA57DAA4FE165D5C75894505B876BA6E4" => Coin::USDC,
-                    "uosmo" => Coin::OSMO,
-                    // TEST coins
-                    "factory/osmo109ns4u04l44kqdkvp876hukd3hxz8zzm7809el/uusdc" => Coin::TUSDC,
-                    // Handle other coins as needed
-                    _ => continue, // Skip unknown denominations
+                let coin = match Coin::from_denom(&balance.denom) {
+                    Some(coin) => coin,
+                    None => continue, // Skip unknown denominations
                 };
                 result.push(CoinAmount { coin, amount });
             }
@@ -86,10 +70,14 @@ pub async fn fetch_balances(address: &str, coins: Option<Vec<Coin>>) -> Result<V
 }
 
 
-fn get_account_balance_url() -> String {
-    CONFIG.osmosis_account_balances_url.clone()
-}
+/// Balance of any denomination, e.g. pool shares which do not fit the known coins
+pub async fn fetch_denom_balance(address: &str, denom: &str) -> Result<u128, Box<dyn StdError>> {
+    let (balances, _) = with_failover(EndpointRole::AccountBalances, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", address)).send().await?.error_for_status()?.json::<BalancesResponse>().await
+    }).await?;
 
-pub fn get_osmosis_account_info_url() -> String {
-    CONFIG.osmosis_account_info_url.clone()
+    match balances.balances.iter().find(|balance| balance.denom == denom) {
+        Some(balance) => Ok(balance.amount.parse()?),
+        None => Ok(0),
+    }
 }
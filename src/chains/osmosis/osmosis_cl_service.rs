@@ -0,0 +1,339 @@
+use std::error::Error as StdError;
+use std::fmt;
+use serde::Deserialize;
+use serde_json::json;
+use log::info;
+use cosmrs::Any;
+use prost::Message;
+use anyhow::{anyhow, bail, Result};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
+use crate::chains::coin::{Coin, CoinAmount};
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::trade_outcome::TradeOutcome;
+use super::osmosis_endpoint_service::{with_failover, EndpointRole, HTTP_CLIENT};
+use super::osmosis_tx_builder::TxBuilder;
+
+// osmosis-std 0.1.6 predates the concentrated liquidity module, its messages are declared here
+
+#[derive(Clone, PartialEq, Message)]
+struct MsgCreatePosition {
+    #[prost(uint64, tag = "1")]
+    pool_id: u64,
+    #[prost(string, tag = "2")]
+    sender: String,
+    #[prost(int64, tag = "3")]
+    lower_tick: i64,
+    #[prost(int64, tag = "4")]
+    upper_tick: i64,
+    #[prost(message, repeated, tag = "5")]
+    tokens_provided: Vec<OsmosisCoin>,
+    #[prost(string, tag = "6")]
+    token_min_amount0: String,
+    #[prost(string, tag = "7")]
+    token_min_amount1: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MsgAddToPosition {
+    #[prost(uint64, tag = "1")]
+    position_id: u64,
+    #[prost(string, tag = "2")]
+    sender: String,
+    #[prost(string, tag = "3")]
+    amount0: String,
+    #[prost(string, tag = "4")]
+    amount1: String,
+    #[prost(string, tag = "5")]
+    token_min_amount0: String,
+    #[prost(string, tag = "6")]
+    token_min_amount1: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MsgWithdrawPosition {
+    #[prost(uint64, tag = "1")]
+    position_id: u64,
+    #[prost(string, tag = "2")]
+    sender: String,
+    /// Dec encoded as its integer atomics, 18 decimals
+    #[prost(string, tag = "3")]
+    liquidity_amount: String,
+}
+
+/// Shared by MsgCollectSpreadRewards and MsgCollectIncentives
+#[derive(Clone, PartialEq, Message)]
+struct MsgCollect {
+    #[prost(uint64, repeated, tag = "1")]
+    position_ids: Vec<u64>,
+    #[prost(string, tag = "2")]
+    sender: String,
+}
+
+const DEC_PRECISION: usize = 18;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DenomAmount {
+    pub denom: String,
+    pub amount: String,
+}
+
+impl fmt::Display for DenomAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (Coin::from_denom(&self.denom), self.amount.parse::<u64>()) {
+            (Some(coin), Ok(amount)) => write!(f, "{}", CoinAmount { coin, amount }),
+            _ => write!(f, "{} {}", self.amount, self.denom),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PositionsResponse {
+    positions: Vec<FullPosition>,
+}
+#[derive(Deserialize, Debug)]
+struct FullPosition {
+    position: PositionData,
+    asset0: DenomAmount,
+    asset1: DenomAmount,
+    #[serde(default)]
+    claimable_spread_rewards: Vec<DenomAmount>,
+    #[serde(default)]
+    claimable_incentives: Vec<DenomAmount>,
+}
+#[derive(Deserialize, Debug)]
+struct PositionData {
+    position_id: String,
+    pool_id: String,
+    lower_tick: String,
+    upper_tick: String,
+    liquidity: String,
+}
+
+/// A concentrated liquidity position of an account
+#[derive(Debug, Clone)]
+pub struct ClPosition {
+    pub position_id: u64,
+    pub pool_id: u64,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    /// liquidity as a decimal string
+    pub liquidity: String,
+    pub asset0: DenomAmount,
+    pub asset1: DenomAmount,
+    pub spread_rewards: Vec<DenomAmount>,
+    pub incentives: Vec<DenomAmount>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClPoolData {
+    pool: ClPoolState,
+}
+/// Current state of a concentrated liquidity pool
+#[derive(Deserialize, Debug)]
+pub struct ClPoolState {
+    pub token0: String,
+    pub token1: String,
+    current_tick: String,
+    current_sqrt_price: String,
+}
+
+impl ClPoolState {
+    pub fn current_tick(&self) -> Result<i64> {
+        self.current_tick.parse().map_err(|e| anyhow!("Failed to parse current_tick: {}", e))
+    }
+
+    /// Spot price of token0 in token1, without the spread factor
+    pub fn price(&self) -> Result<f64> {
+        let sqrt_price: f64 = self.current_sqrt_price.parse().map_err(|e| anyhow!("Failed to parse sqrt_price: {}", e))?;
+        Ok(sqrt_price * sqrt_price)
+    }
+}
+
+/// Valuation of a position at the current pool state
+#[derive(Debug, Clone)]
+pub struct PositionStatus {
+    pub in_range: bool,
+    /// value of both assets in the pool's token1, in microns
+    pub value: u64,
+    pub value_denom: String,
+}
+
+impl ClPosition {
+    /// In range when the current tick is within [lower_tick, upper_tick)
+    pub fn status(&self, pool: &ClPoolState) -> Result<PositionStatus> {
+        let current_tick = pool.current_tick()?;
+        let amount0: f64 = self.asset0.amount.parse().map_err(|e| anyhow!("Failed to parse asset0 amount: {}", e))?;
+        let amount1: f64 = self.asset1.amount.parse().map_err(|e| anyhow!("Failed to parse asset1 amount: {}", e))?;
+
+        Ok(PositionStatus {
+            in_range: self.lower_tick <= current_tick && current_tick < self.upper_tick,
+            value: (amount0 * pool.price()? + amount1) as u64,
+            value_denom: pool.token1.clone(),
+        })
+    }
+}
+
+impl fmt::Display for ClPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} pool {} ticks [{}, {}): {} + {}", self.position_id, self.pool_id, self.lower_tick, self.upper_tick, self.asset0, self.asset1)
+    }
+}
+
+/// Fetches the concentrated liquidity positions of an account
+pub async fn fetch_positions(address: &str) -> Result<Vec<ClPosition>, Box<dyn StdError>> {
+    let (response, _) = with_failover(EndpointRole::ClPositions, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", address)).send().await?.error_for_status()?.json::<PositionsResponse>().await
+    }).await?;
+
+    let mut positions = Vec::new();
+    for full in response.positions {
+        positions.push(ClPosition {
+            position_id: full.position.position_id.parse()?,
+            pool_id: full.position.pool_id.parse()?,
+            lower_tick: full.position.lower_tick.parse()?,
+            upper_tick: full.position.upper_tick.parse()?,
+            liquidity: full.position.liquidity,
+            asset0: full.asset0,
+            asset1: full.asset1,
+            spread_rewards: full.claimable_spread_rewards,
+            incentives: full.claimable_incentives,
+        });
+    }
+
+    Ok(positions)
+}
+
+/// Fetches the position of an account with the given id
+pub async fn fetch_position(address: &str, position_id: u64) -> Result<ClPosition> {
+    fetch_positions(address)
+        .await
+        .map_err(|e| anyhow!("Error fetching positions: {}", e))?
+        .into_iter()
+        .find(|position| position.position_id == position_id)
+        .ok_or_else(|| anyhow!("Position {} not found for account {}", position_id, address))
+}
+
+/// Fetches the state of a concentrated liquidity pool
+pub async fn fetch_cl_pool(pool_id: u64) -> Result<ClPoolState> {
+    let (raw_json, provider) = with_failover(EndpointRole::PoolPrice, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", pool_id.to_string().as_str())).send().await?.error_for_status()?.text().await
+    }).await.map_err(|e| anyhow!("Error fetching pool {}: {}", pool_id, e))?;
+    info!("Pool {} fetched from {}", pool_id, provider);
+
+    let data: ClPoolData = serde_json::from_str(&raw_json).map_err(|e| anyhow!("Pool {} is not a concentrated liquidity pool: {}", pool_id, e))?;
+    Ok(data.pool)
+}
+
+/// Opens a position over [lower_tick, upper_tick) with the given amounts of the pool tokens, in microns
+pub async fn create_position(signer: &Signer, pool_id: u64, lower_tick: i64, upper_tick: i64, amounts: (u64, u64), min_amounts: (u64, u64)) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let pool = fetch_cl_pool(pool_id).await?;
+
+    // Step 1. Create the message, zero amounts are left out
+    let tokens_provided: Vec<OsmosisCoin> = [(&pool.token0, amounts.0), (&pool.token1, amounts.1)]
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0)
+        .map(|(denom, amount)| OsmosisCoin { denom: denom.clone(), amount: amount.to_string() })
+        .collect();
+    let msg = MsgCreatePosition {
+        pool_id,
+        sender: sender.to_string(),
+        lower_tick,
+        upper_tick,
+        tokens_provided: tokens_provided.clone(),
+        token_min_amount0: min_amounts.0.to_string(),
+        token_min_amount1: min_amounts.1.to_string(),
+    };
+
+    // Step 2. Sign and broadcast
+    TxBuilder::new(signer).message(to_any("MsgCreatePosition", &msg)).broadcast(json!({
+        "action": "cl_create_position",
+        "pool_id": pool_id,
+        "lower_tick": lower_tick,
+        "upper_tick": upper_tick,
+        "tokens_provided": tokens_provided.iter().map(|coin| json!({"denom": coin.denom, "amount": coin.amount})).collect::<Vec<_>>(),
+    })).await
+}
+
+/// Adds the given amounts of the pool tokens to a position, in microns
+pub async fn add_to_position(signer: &Signer, position_id: u64, amounts: (u64, u64), min_amounts: (u64, u64)) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let position = fetch_position(sender, position_id).await?;
+
+    let msg = MsgAddToPosition {
+        position_id,
+        sender: sender.to_string(),
+        amount0: amounts.0.to_string(),
+        amount1: amounts.1.to_string(),
+        token_min_amount0: min_amounts.0.to_string(),
+        token_min_amount1: min_amounts.1.to_string(),
+    };
+
+    TxBuilder::new(signer).message(to_any("MsgAddToPosition", &msg)).broadcast(json!({
+        "action": "cl_add_to_position",
+        "pool_id": position.pool_id,
+        "position_id": position_id,
+        "amount0": amounts.0,
+        "amount1": amounts.1,
+    })).await
+}
+
+/// Withdraws a share of the position liquidity, the whole position at 100%
+pub async fn withdraw_position(signer: &Signer, position_id: u64, share_pct: f64) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let position = fetch_position(sender, position_id).await?;
+
+    let liquidity = if share_pct >= 100.0 {
+        position.liquidity.clone()
+    } else {
+        let liquidity: f64 = position.liquidity.parse().map_err(|e| anyhow!("Failed to parse liquidity: {}", e))?;
+        format!("{:.*}", DEC_PRECISION, liquidity * share_pct / 100.0)
+    };
+
+    let msg = MsgWithdrawPosition {
+        position_id,
+        sender: sender.to_string(),
+        liquidity_amount: dec_atomics(&liquidity)?,
+    };
+
+    TxBuilder::new(signer).message(to_any("MsgWithdrawPosition", &msg)).broadcast(json!({
+        "action": "cl_withdraw_position",
+        "pool_id": position.pool_id,
+        "position_id": position_id,
+        "liquidity": liquidity,
+    })).await
+}
+
+/// Collects the spread rewards of the positions, and their incentives when asked
+pub async fn collect(signer: &Signer, position_ids: Vec<u64>, incentives: bool) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let msg = MsgCollect { position_ids: position_ids.clone(), sender: sender.to_string() };
+
+    let mut messages = vec![to_any("MsgCollectSpreadRewards", &msg)];
+    if incentives {
+        messages.push(to_any("MsgCollectIncentives", &msg));
+    }
+
+    TxBuilder::new(signer).messages(messages).broadcast(json!({
+        "action": if incentives { "cl_collect_rewards_and_incentives" } else { "cl_collect_rewards" },
+        "position_ids": position_ids,
+    })).await
+}
+
+fn to_any<M: Message>(name: &str, msg: &M) -> Any {
+    Any {
+        type_url: format!("/osmosis.concentratedliquidity.v1beta1.{}", name),
+        value: msg.encode_to_vec(),
+    }
+}
+
+// Decimal string to the integer atomics a Dec is encoded with
+fn dec_atomics(dec: &str) -> Result<String> {
+    let (integer, fraction) = dec.split_once('.').unwrap_or((dec, ""));
+    if fraction.len() > DEC_PRECISION || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        bail!("Invalid decimal '{}'", dec);
+    }
+    let atomics = format!("{}{:0<width$}", integer, fraction, width = DEC_PRECISION);
+    let atomics = atomics.trim_start_matches('0');
+    Ok(if atomics.is_empty() { "0".to_string() } else { atomics.to_string() })
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use url::Url;
+use log::{debug, info, warn};
+use crate::config::{CONFIG, EndpointList};
+
+/// Weight of the latest sample in the latency moving average
+const LATENCY_SMOOTHING: f64 = 0.3;
+/// Timeout of a single health check request
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The different kinds of requests sent to the Osmosis providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndpointRole {
+    Status,
+    AccountInfo,
+    BroadcastTx,
+    GrpcBroadcastTx,
+    PoolPrice,
+    AccountBalances,
+    TxDetails,
+    ClPositions,
+    TxSearch,
+}
+
+impl EndpointRole {
+    const ALL: [EndpointRole; 9] = [
+        EndpointRole::Status,
+        EndpointRole::AccountInfo,
+        EndpointRole::BroadcastTx,
+        EndpointRole::GrpcBroadcastTx,
+        EndpointRole::PoolPrice,
+        EndpointRole::AccountBalances,
+        EndpointRole::TxDetails,
+        EndpointRole::ClPositions,
+        EndpointRole::TxSearch,
+    ];
+
+    fn configured_urls(&self) -> &'static EndpointList {
+        match self {
+            EndpointRole::Status => &CONFIG.osmosis_status_url,
+            EndpointRole::AccountInfo => &CONFIG.osmosis_account_info_url,
+            EndpointRole::BroadcastTx => &CONFIG.osmosis_broadcast_tx_url,
+            EndpointRole::GrpcBroadcastTx => &CONFIG.broadcast_config.grpc_url,
+            EndpointRole::PoolPrice => &CONFIG.osmosis_pool_price_url,
+            EndpointRole::AccountBalances => &CONFIG.osmosis_account_balances_url,
+            EndpointRole::TxDetails => &CONFIG.osmosis_tx_details_url,
+            EndpointRole::ClPositions => &CONFIG.osmosis_cl_positions_url,
+            EndpointRole::TxSearch => &CONFIG.osmosis_tx_search_url,
+        }
+    }
+}
+
+/// A single provider URL and its circuit breaker state
+struct Provider {
+    url: String,
+    state: Mutex<ProviderState>,
+}
+
+#[derive(Default)]
+struct ProviderState {
+    consecutive_failures: u32,
+    /// while set and in the future, the circuit is open and the provider is skipped
+    open_until: Option<Instant>,
+    /// moving average of the response time, in milliseconds
+    latency_ms: Option<f64>,
+}
+
+impl Provider {
+    fn new(url: &str) -> Self {
+        Provider {
+            url: url.to_string(),
+            state: Mutex::new(ProviderState::default()),
+        }
+    }
+
+    fn record_success(&self, elapsed: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let sample = elapsed.as_secs_f64() * 1000.0;
+        state.latency_ms = Some(match state.latency_ms {
+            Some(latency) => latency * (1.0 - LATENCY_SMOOTHING) + sample * LATENCY_SMOOTHING,
+            None => sample,
+        });
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    fn record_failure(&self) {
+        let config = &CONFIG.endpoint_config;
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= config.failure_threshold {
+            // (re)open the circuit, a single request is let through once the cooldown expires
+            state.open_until = Some(Instant::now() + Duration::from_secs(config.cooldown_secs));
+            warn!("!!! Circuit opened for provider {} after {} consecutive failures", self.url, state.consecutive_failures);
+        }
+    }
+
+    /// Sort key: available providers first, then the fastest ones.
+    /// Providers without latency samples yet come first so they get measured.
+    fn rank(&self, now: Instant) -> (bool, u64) {
+        let state = self.state.lock().unwrap();
+        let open = state.open_until.is_some_and(|until| until > now);
+        (open, state.latency_ms.map_or(0, |latency| latency as u64))
+    }
+}
+
+static PROVIDERS: Lazy<HashMap<EndpointRole, Vec<Provider>>> = Lazy::new(|| {
+    EndpointRole::ALL
+        .iter()
+        .map(|role| (*role, role.configured_urls().iter().map(|url| Provider::new(url)).collect()))
+        .collect()
+});
+
+/// HTTP client shared by every provider request, a provider that hangs
+/// past the request timeout is failed over like one that errors
+pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(CONFIG.endpoint_config.request_timeout_secs))
+        .build()
+        .expect("failed to build http client")
+});
+
+fn ranked_providers(role: EndpointRole) -> Vec<&'static Provider> {
+    let now = Instant::now();
+    let mut providers: Vec<&Provider> = PROVIDERS[&role].iter().collect();
+    providers.sort_by_key(|provider| provider.rank(now));
+    providers
+}
+
+/// URLs of the providers of the given role, best ranked first
+pub fn provider_urls(role: EndpointRole) -> Vec<String> {
+    ranked_providers(role).iter().map(|provider| provider.url.clone()).collect()
+}
+
+/// Why a request failed over every provider it was sent to
+#[derive(Debug)]
+pub enum FailoverError {
+    /// every provider failed on an error `may_retry` accepts, or none is configured
+    Exhausted(String),
+    /// a provider failed on an error `may_retry` does not accept, the next ones were not tried
+    Stopped(String),
+}
+
+impl fmt::Display for FailoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailoverError::Exhausted(error) | FailoverError::Stopped(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for FailoverError {}
+
+/// Runs `request` against the providers of the given role, best ranked first,
+/// failing over to the next provider on error.
+/// Returns the response along with the URL of the provider that served it.
+pub async fn with_failover<T, E, F, Fut>(role: EndpointRole, request: F) -> Result<(T, String), anyhow::Error>
+where
+    E: Display,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    Ok(with_failover_if(role, request, |_| true).await?)
+}
+
+/// Same as `with_failover`, but only fails over on the errors `may_retry` accepts,
+/// any other error is returned right away as `FailoverError::Stopped`
+pub async fn with_failover_if<T, E, F, Fut, R>(role: EndpointRole, request: F, may_retry: R) -> Result<(T, String), FailoverError>
+where
+    E: Display,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    R: Fn(&E) -> bool,
+{
+    let mut last_error = None;
+
+    for provider in ranked_providers(role) {
+        let start = Instant::now();
+        match request(provider.url.clone()).await {
+            Ok(response) => {
+                provider.record_success(start.elapsed());
+                debug!("{:?} request served by {}", role, provider.url);
+                return Ok((response, provider.url.clone()));
+            }
+            Err(e) => {
+                warn!("!!! {:?} request to {} failed: {}", role, provider.url, e);
+                provider.record_failure();
+                if !may_retry(&e) {
+                    return Err(FailoverError::Stopped(format!("{:?} request to {} failed: {}", role, provider.url, e)));
+                }
+                last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    Err(FailoverError::Exhausted(format!("All {:?} providers failed, last error: {}", role, last_error.unwrap_or_else(|| "no provider configured".to_string()))))
+}
+
+/// Probes every configured provider on a fixed interval, so latencies stay
+/// fresh and open circuits are closed as soon as the provider recovers.
+pub async fn run_health_checks() {
+    let interval = CONFIG.endpoint_config.health_check_interval_secs;
+    if interval == 0 {
+        return;
+    }
+
+    let client = Client::builder()
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .build()
+        .expect("failed to build health check client");
+
+    loop {
+        for role in EndpointRole::ALL {
+            for provider in PROVIDERS[&role].iter() {
+                check_provider(&client, provider).await;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+// Any answer below 500 from the provider origin means the server is up
+async fn check_provider(client: &Client, provider: &Provider) {
+    let origin = match Url::parse(&provider.url) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(e) => {
+            warn!("!!! Invalid provider url {}: {}", provider.url, e);
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    match client.get(&origin).send().await {
+        Ok(response) if !response.status().is_server_error() => {
+            provider.record_success(start.elapsed());
+        }
+        Ok(response) => {
+            info!("Health check of {} returned {}", origin, response.status());
+            provider.record_failure();
+        }
+        Err(e) => {
+            info!("Health check of {} failed: {}", origin, e);
+            provider.record_failure();
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use serde_json::json;
+use log::info;
+use cosmrs::Any;
+use prost::Message;
+use anyhow::{anyhow, bail, Result};
+use osmosis_std::types::osmosis::gamm::v1beta1::{MsgExitPool, MsgJoinPool, MsgJoinSwapExternAmountIn};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_account_service::fetch_denom_balance;
+use crate::trade_outcome::TradeOutcome;
+use super::osmosis_pool_service::fetch_gamm_pool;
+use super::osmosis_tx_builder::TxBuilder;
+
+/// Joins a pool with at most the given amounts of its assets, in pool order.
+/// A single non zero amount joins with that asset alone, swapping part of it.
+pub async fn join_pool(signer: &Signer, pool_id: u64, amounts: &[u64], max_slippage_bps: u64) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let pool = fetch_gamm_pool(pool_id).await?;
+    let denoms = pool.denoms();
+    if amounts.len() != denoms.len() {
+        bail!("Pool {} has {} assets, {} amounts provided", pool_id, denoms.len(), amounts.len());
+    }
+    let slippage = 1.0 - max_slippage_bps as f64 / 10_000.0;
+
+    let provided: Vec<(&str, u64)> = denoms.iter().copied().zip(amounts.iter().copied()).filter(|(_, amount)| *amount > 0).collect();
+    let (msg, expected_shares, min_shares) = match provided.as_slice() {
+        [] => bail!("No amount provided to join pool {}", pool_id),
+        // Step 1a. Single asset join, bounded by the min shares out
+        [(denom, amount)] => {
+            let expected_shares = pool.expected_single_join_shares(denom, *amount)?;
+            let min_shares = (expected_shares as f64 * slippage) as u128;
+            let msg = MsgJoinSwapExternAmountIn {
+                sender: sender.to_string(),
+                pool_id,
+                token_in: Some(OsmosisCoin { denom: denom.to_string(), amount: amount.to_string() }),
+                share_out_min_amount: min_shares.to_string(),
+            };
+            (to_any("MsgJoinSwapExternAmountIn", &msg), expected_shares, min_shares)
+        }
+        // Step 1b. Balanced join, asks for fewer shares than expected so the max amounts are not exceeded
+        _ => {
+            let expected_shares = pool.expected_join_shares(amounts)?;
+            let min_shares = (expected_shares as f64 * slippage) as u128;
+            let msg = MsgJoinPool {
+                sender: sender.to_string(),
+                pool_id,
+                share_out_amount: min_shares.to_string(),
+                token_in_maxs: sorted_coins(&provided),
+            };
+            (to_any("MsgJoinPool", &msg), expected_shares, min_shares)
+        }
+    };
+    if min_shares == 0 {
+        bail!("Amounts are too small to receive shares of pool {}", pool_id);
+    }
+    info!(">>> Joining pool {} for {} shares, at least {}", pool_id, expected_shares, min_shares);
+
+    // Step 2. Sign and broadcast
+    TxBuilder::new(signer).message(msg).broadcast(json!({
+        "action": "lp_join",
+        "pool_id": pool_id,
+        "share_denom": pool.share_denom(),
+        "tokens_provided": coins_json(&provided),
+        "expected_shares": expected_shares.to_string(),
+        "min_shares": min_shares.to_string(),
+    })).await
+}
+
+/// Exits a share of the pool shares held by the account, all of them at 100%
+pub async fn exit_pool(signer: &Signer, pool_id: u64, share_pct: f64, max_slippage_bps: u64) -> Result<TradeOutcome> {
+    let sender = signer.get_account_address();
+    let pool = fetch_gamm_pool(pool_id).await?;
+
+    // Step 1. Read the shares held
+    let held = fetch_denom_balance(sender, pool.share_denom()).await.map_err(|e| anyhow!("Error fetching {} balance: {}", pool.share_denom(), e))?;
+    let shares = if share_pct >= 100.0 { held } else { (held as f64 * share_pct / 100.0) as u128 };
+    if shares == 0 {
+        bail!("Account {} holds no shares of pool {}", sender, pool_id);
+    }
+
+    // Step 2. Bound the amounts out by the expected ones
+    let slippage = 1.0 - max_slippage_bps as f64 / 10_000.0;
+    let expected: Vec<(&str, u64)> = pool.denoms().into_iter().zip(pool.expected_exit_amounts(shares)?).collect();
+    let mins: Vec<(&str, u64)> = expected.iter().map(|(denom, amount)| (*denom, (*amount as f64 * slippage) as u64)).collect();
+    info!(">>> Exiting pool {} with {} shares", pool_id, shares);
+
+    let msg = MsgExitPool {
+        sender: sender.to_string(),
+        pool_id,
+        share_in_amount: shares.to_string(),
+        token_out_mins: sorted_coins(&mins),
+    };
+
+    // Step 3. Sign and broadcast
+    TxBuilder::new(signer).message(to_any("MsgExitPool", &msg)).broadcast(json!({
+        "action": "lp_exit",
+        "pool_id": pool_id,
+        "share_denom": pool.share_denom(),
+        "shares": shares.to_string(),
+        "expected_tokens": coins_json(&expected),
+        "min_tokens": coins_json(&mins),
+    })).await
+}
+
+// Coins of a message must be sorted by denom
+fn sorted_coins(coins: &[(&str, u64)]) -> Vec<OsmosisCoin> {
+    let mut coins: Vec<OsmosisCoin> = coins
+        .iter()
+        .map(|(denom, amount)| OsmosisCoin { denom: denom.to_string(), amount: amount.to_string() })
+        .collect();
+    coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+    coins
+}
+
+fn coins_json(coins: &[(&str, u64)]) -> Vec<serde_json::Value> {
+    coins.iter().map(|(denom, amount)| json!({"denom": denom, "amount": amount})).collect()
+}
+
+fn to_any<M: Message>(name: &str, msg: &M) -> Any {
+    Any {
+        type_url: format!("/osmosis.gamm.v1beta1.{}", name),
+        value: msg.encode_to_vec(),
+    }
+}
@@ -1,29 +1,29 @@
 use serde::Deserialize;
 use std::error::Error as StdError;
-use reqwest;
 use crate::config::CONFIG;
 use crate::chains::coin::Coin;
-use crate::chains::chain::ChainType;
 use crate::chains::osmosis::osmosis_key_service::Signer;
-use crate::chains::osmosis::osmosis_account_service::fetch_account_info;
 use super::osmosis_transaction::broadcast_tx;
+use super::osmosis_tx_builder::{FeeStrategy, TxBuilder};
+use crate::trade_outcome::TradeOutcome;
+use crate::market_maker::MarketSide;
+use super::osmosis_endpoint_service::{with_failover, provider_urls, EndpointRole, HTTP_CLIENT};
 
 use osmosis_std::types::osmosis::gamm::v1beta1::{MsgSwapExactAmountOut, SwapAmountOutRoute, MsgSwapExactAmountIn, SwapAmountInRoute};
 use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
 
 use serde_json::json;
-use cosmrs::proto::cosmos::tx::v1beta1::{SimulateRequest, SimulateResponse};
 
-use cosmrs::tendermint::{block::Height, chain::Id};
-use cosmrs::tx::{Body, Fee, AuthInfo, SignDoc, Tx};
+use cosmrs::tx::Tx;
 use cosmrs::Any;
-use cosmrs::Coin as CosmosCoin;
-use cosmrs::Decimal;
 
 use anyhow::Result;
 use prost::Message;
 
-use reqwest::Client;
+use log::{info, warn};
+use url::Url;
+use rand::Rng;
+use chrono::{Duration as ChronoDuration, SecondsFormat, Utc};
 
 // TODO: WIP Function to simulate a transaction cost
 pub async fn simulate_tx(tx: Tx) -> Result<()> {
@@ -41,9 +41,8 @@ pub async fn simulate_tx(tx: Tx) -> Result<()> {
     });
 
     // Step 4: Make the request to the simulate endpoint
-    let client = Client::new();
     let simulate_url = "https://lcd.osmotest5.osmosis.zone/cosmos/tx/v1beta1/simulate".to_string();
-    let response = client
+    let response = HTTP_CLIENT
         .post(simulate_url)
         .json(&simulate_body)
         .send()
@@ -65,68 +64,103 @@ pub async fn simulate_tx(tx: Tx) -> Result<()> {
     Ok(())
 }
 
-pub async fn perform_swap(
-    signer: &Signer,
-    pool_id: u64,
-    coin_in: Coin,
-    coin_out: Coin,    
-    amount: u64,
-    swap_type: &str,
-    min_price: f64,
-) -> Result<bool, anyhow::Error> {
+/// Parameters of a single swap
+pub struct SwapOrder {
+    pub pool_id: u64,
+    pub coin_in: Coin,
+    pub coin_out: Coin,
+    pub amount: u64,
+    pub swap_type: &'static str,
+    pub min_price: f64,
+    /// max distance from the fresh quote the swap may execute at, in basis points
+    pub max_slippage_bps: Option<u64>,
+    /// min price of the ladder tier this swap fills
+    pub ladder_tier: Option<f64>,
+    /// side of a market making trade
+    pub market_side: Option<MarketSide>,
+    /// swap of a rebalance, not part of a stream campaign
+    pub rebalance: bool,
+    /// ledger action of a swap that is not a trade, e.g. gas_top_up
+    pub action: Option<&'static str>,
+}
+
+/// Price bounds of a swap, computed right before signing
+pub struct SwapQuote {
+    /// pool price quoted right before signing
+    pub quoted_price: Option<f64>,
+    /// worst price accepted by the swap message
+    pub limit_price: f64,
+}
+
+impl SwapOrder {
+    /// Takes a fresh quote and picks the tighter bound between `min_price`
+    /// and the quote minus the allowed slippage
+    pub async fn quote(&self) -> Result<SwapQuote> {
+        // Oriented by the pool asset order, the pool may not trade the configured tokens
+        let price = match fetch_pool_price(self.pool_id).await {
+            Ok(pool_price) => pool_price.price_of(self.coin_in, self.coin_out),
+            Err(e) => Err(anyhow::anyhow!("{}", e)),
+        };
+        let quoted_price = match price {
+            Ok(price) => Some(price),
+            Err(e) if self.max_slippage_bps.is_some() => {
+                return Err(anyhow::anyhow!("Failed to quote price to bound slippage: {}", e));
+            }
+            Err(e) => {
+                warn!("!!! Failed to quote price before signing: {}", e);
+                None
+            }
+        };
+
+        let limit_price = match (quoted_price, self.max_slippage_bps) {
+            (Some(price), Some(bps)) => {
+                let slippage_bound = price * (1.0 - bps as f64 / 10_000.0);
+                slippage_bound.max(self.min_price)
+            }
+            _ => self.min_price,
+        };
+
+        Ok(SwapQuote { quoted_price, limit_price })
+    }
+}
+
+pub async fn perform_swap(signer: &Signer, order: &SwapOrder) -> Result<TradeOutcome, anyhow::Error> {
     
     // Step 1. Get the sender address
     let sender_address = signer.get_account_address();
 
-    // Step 2. Create the swap message
-    let msg_swap = match swap_type {
-        "amount_out" => create_msg_swap_exact_amount_out(sender_address, pool_id, coin_in, coin_out, amount, min_price),
-        "amount_in" => create_msg_swap_exact_amount_in(sender_address, pool_id, coin_in, coin_out, amount, min_price),
-        _ => Err(anyhow::anyhow!("Invalid swap type: {}", swap_type)),
+    // Step 2. Create the swap message, bounded by the fresh quote
+    let quote = order.quote().await?;
+    info!(">>> Quoted price {:?}, limit price {}", quote.quoted_price, quote.limit_price);
+    let msg_swap = match order.swap_type {
+        "amount_out" => create_msg_swap_exact_amount_out(sender_address, order.pool_id, order.coin_in, order.coin_out, order.amount, quote.limit_price),
+        "amount_in" => create_msg_swap_exact_amount_in(sender_address, order.pool_id, order.coin_in, order.coin_out, order.amount, quote.limit_price),
+        _ => Err(anyhow::anyhow!("Invalid swap type: {}", order.swap_type)),
     }?;
 
-    // Step 3. Get the current block height
-    let current_height = get_current_block_height().await.map_err(|e| anyhow::anyhow!("Failed to get current block height: {}", e))?;
-    let timeout_height = current_height + 200;  // Set a future timeout height
-
-    // Step 4. Create TxBody
-    let tx_body = Body {
-        messages: vec![msg_swap],
-        memo: "Trade Stream".to_string(),
-        timeout_height: Height::try_from(timeout_height).unwrap(),
-        extension_options: vec![],
-        non_critical_extension_options: vec![],
-    };
-
-    // Step 5. Fetch account sequence and get signer info
-    let (account_number, sequence) = fetch_account_info(sender_address).await.map_err(|e| anyhow::anyhow!("Failed to fetch account info: {}", e))?;
-    let signer_info = signer.create_signer_info(sequence);
-    
-    // Step 6: Create AuthInfo with fee details
-    let fee = Fee::from_amount_and_gas(CosmosCoin {
-        denom: "uosmo".parse().unwrap(),
-        amount: Decimal::from(CONFIG.gas_config.amount),
-    }, CONFIG.gas_config.gas_limit);
-    let auth_info = AuthInfo {
-        signer_infos: vec![signer_info],
-        fee,
-    };  
-
-    // Step 7: Create and sign the doc
-    let comos_id = ChainType::Osmosis.chain_id();
-    let chain_id = Id::try_from(comos_id.clone())?;
-    let sign_doc = SignDoc::new(&tx_body, &auth_info, &chain_id, account_number).map_err(|e| anyhow::anyhow!("Failed to create SignDoc: {}", e))?;
-    let tx_bytes = signer.sign_doc(sign_doc).map_err(|e| anyhow::anyhow!("Failed to sign the transaction: {}", e))?;
-
-    // Step 8: Create and broadcast the transaction
-    let tx_parsed = Tx::from_bytes(&tx_bytes).map_err(|e| anyhow::anyhow!("Failed to parse transaction bytes: {}", e))?;
+    // Step 3. Sign and broadcast the transaction
+    let tx_parsed = TxBuilder::new(signer)
+        .message(msg_swap)
+        .memo(&pick_memo())
+        .fee(FeeStrategy::Jittered)
+        .sign()
+        .await?;
     // simulate_tx(tx_parsed.clone()).await?;
-    broadcast_tx(tx_parsed, sender_address, pool_id, coin_in, coin_out, amount, swap_type, min_price).await
+    broadcast_tx(tx_parsed, sender_address, order, &quote).await
 }
 
-fn create_msg_swap_exact_amount_out(sender_address: &str, pool_id: u64, coin_in: Coin, coin_out: Coin, amount: u64, min_price: f64) -> Result<Any> {
+// A random memo from the footprint config, the default memo when none is configured
+fn pick_memo() -> String {
+    let memos = &CONFIG.footprint.memos;
+    if memos.is_empty() {
+        return "Trade Stream".to_string();
+    }
+    memos[rand::thread_rng().gen_range(0..memos.len())].clone()
+}
+
+fn create_msg_swap_exact_amount_out(sender_address: &str, pool_id: u64, coin_in: Coin, coin_out: Coin, amount: u64, limit_price: f64) -> Result<Any> {
     // Step 1. Calc max token in amount
-    let token_in_max_amount: u64 = (amount as f64 / min_price) as u64;
+    let token_in_max_amount: u64 = (amount as f64 / limit_price) as u64;
 
     // Step 2. Create swap message
     let msg_swap = MsgSwapExactAmountOut {
@@ -148,9 +182,9 @@ fn create_msg_swap_exact_amount_out(sender_address: &str, pool_id: u64, coin_in:
     })
 }
 
-fn create_msg_swap_exact_amount_in(sender_address: &str, pool_id: u64, coin_in: Coin, coin_out: Coin, amount: u64, min_price: f64) -> Result<Any> {
+fn create_msg_swap_exact_amount_in(sender_address: &str, pool_id: u64, coin_in: Coin, coin_out: Coin, amount: u64, limit_price: f64) -> Result<Any> {
     // Step 1. Calc min token out amount
-    let token_out_min_amount: u64 = (amount as f64 * min_price) as u64;
+    let token_out_min_amount: u64 = (amount as f64 * limit_price) as u64;
 
     // Step 2. Create swap message
     let msg_swap = MsgSwapExactAmountIn {
@@ -188,14 +222,10 @@ struct SyncInfoWrapper {
 }
 
 pub async fn get_current_block_height() -> Result<u64, Box<dyn StdError>> {
-    let client = Client::new();
-    let url = get_osmosis_rpc_url();
-    let res = client
-        .get(url)  // Replace with the actual RPC endpoint URL
-        .send()
-        .await?
-        .json::<StatusResponse>()
-        .await?;
+    let (res, _) = with_failover(EndpointRole::Status, |url| {
+        let request = HTTP_CLIENT.get(url).send();
+        async move { request.await?.error_for_status()?.json::<StatusResponse>().await }
+    }).await?;
 
     let height = res.result.sync_info.latest_block_height.parse::<u64>()?;
     Ok(height)
@@ -219,6 +249,8 @@ struct PoolCLData {
 }
 #[derive(Deserialize, Debug)]
 struct PoolCL {
+    token0: String,
+    token1: String,
     current_sqrt_price: String,
     spread_factor: String,
 }
@@ -229,13 +261,15 @@ struct PoolDefaultData {
     pool: PoolDefault,
 }
 #[derive(Deserialize, Debug)]
-struct PoolDefault {
+pub struct PoolDefault {
     pool_params: PoolParams,
     pool_assets: Vec<PoolAsset>,
+    total_shares: Token,
 }
 #[derive(Deserialize, Debug)]
 struct PoolParams {
     swap_fee: String,
+    exit_fee: String,
 }
 #[derive(Deserialize, Debug)]
 struct PoolAsset {
@@ -244,45 +278,188 @@ struct PoolAsset {
 }
 #[derive(Deserialize, Debug)]
 struct Token {
-    //denom: String,
+    denom: String,
     amount: String
 }
 
+impl PoolDefault {
+    /// Denom of the pool shares, e.g. gamm/pool/1
+    pub fn share_denom(&self) -> &str {
+        &self.total_shares.denom
+    }
 
-pub async fn fetch_coin_price(pool_id: u64) -> Result<f64, Box<dyn StdError>> {
-    // subsitua o {} da url por 1721
-    let url = get_osmosis_pool_price_url();
-    let url_formated = url.replace("{}", pool_id.to_string().as_str());
-    let response = reqwest::get(url_formated).await?;
-    let response = response.error_for_status()?;
-    
+    /// Denoms of the pool assets, in pool order
+    pub fn denoms(&self) -> Vec<&str> {
+        self.pool_assets.iter().map(|asset| asset.token.denom.as_str()).collect()
+    }
+
+    /// Shares received for joining with at most the given amounts of each asset, in pool order.
+    /// All assets are deposited in the pool ratio, the most constrained one bounds the shares.
+    pub fn expected_join_shares(&self, amounts: &[u64]) -> Result<u128> {
+        let total_shares: f64 = parse_amount(&self.total_shares.amount)?;
+        let mut ratio = f64::MAX;
+        for (asset, amount) in self.pool_assets.iter().zip(amounts) {
+            ratio = ratio.min(*amount as f64 / parse_amount(&asset.token.amount)?);
+        }
+        Ok((total_shares * ratio) as u128)
+    }
+
+    /// Shares received for joining with a single asset, swapping part of it into the other assets
+    pub fn expected_single_join_shares(&self, denom: &str, amount: u64) -> Result<u128> {
+        let asset = self.pool_assets.iter().find(|asset| asset.token.denom == denom)
+            .ok_or_else(|| anyhow::anyhow!("Denom {} is not in the pool", denom))?;
+        let total_shares = parse_amount(&self.total_shares.amount)?;
+        let total_weight: f64 = self.pool_assets.iter().map(|asset| parse_amount(&asset.weight)).sum::<Result<f64>>()?;
+        let weight = parse_amount(&asset.weight)? / total_weight;
+        let swap_fee = parse_amount(&self.pool_params.swap_fee)?;
+
+        // Only the share of the amount swapped into the other assets pays the swap fee
+        let amount_after_fee = amount as f64 * (1.0 - (1.0 - weight) * swap_fee);
+        let growth = (1.0 + amount_after_fee / parse_amount(&asset.token.amount)?).powf(weight);
+        Ok((total_shares * (growth - 1.0)) as u128)
+    }
+
+    /// Amounts of each asset received for the given shares, in pool order, after the exit fee
+    pub fn expected_exit_amounts(&self, shares: u128) -> Result<Vec<u64>> {
+        let total_shares = parse_amount(&self.total_shares.amount)?;
+        let exit_fee = parse_amount(&self.pool_params.exit_fee)?;
+        let share = shares as f64 / total_shares * (1.0 - exit_fee);
+        self.pool_assets
+            .iter()
+            .map(|asset| Ok((parse_amount(&asset.token.amount)? * share) as u64))
+            .collect()
+    }
+}
+
+fn parse_amount(amount: &str) -> Result<f64> {
+    amount.parse().map_err(|e| anyhow::anyhow!("Failed to parse pool amount '{}': {}", amount, e))
+}
+
+/// Fetches a default (gamm) pool with its assets and shares
+pub async fn fetch_gamm_pool(pool_id: u64) -> Result<PoolDefault> {
+    let (raw_json, provider) = with_failover(EndpointRole::PoolPrice, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", pool_id.to_string().as_str())).send().await?.error_for_status()?.text().await
+    }).await.map_err(|e| anyhow::anyhow!("Error fetching pool {}: {}", pool_id, e))?;
+    info!("Pool {} fetched from {}", pool_id, provider);
+
+    let json_data: PoolDefaultData = serde_json::from_str(&raw_json).map_err(|e| anyhow::anyhow!("Pool {} is not a default pool: {}", pool_id, e))?;
+    Ok(json_data.pool)
+}
+
+
+/// Spot price of a pool, quoted as its second asset per unit of its first asset
+#[derive(Debug, Clone)]
+pub struct PoolPrice {
+    pub base_denom: String,
+    pub quote_denom: String,
+    /// price before the swap fee
+    pub spot: f64,
+    /// swap fee of a default pool, spread factor of a concentrated pool
+    pub swap_fee: f64,
+}
+
+impl PoolPrice {
+    /// Spot price of `coin_in` quoted in `coin_out`, whatever the order of the pool assets
+    pub fn spot_of(&self, coin_in: Coin, coin_out: Coin) -> Result<f64> {
+        if coin_in.denom() == self.base_denom && coin_out.denom() == self.quote_denom {
+            Ok(self.spot)
+        } else if coin_in.denom() == self.quote_denom && coin_out.denom() == self.base_denom {
+            Ok(1.0 / self.spot)
+        } else {
+            Err(anyhow::anyhow!("Pool of {} and {} does not trade {} for {}", self.base_denom, self.quote_denom, coin_in, coin_out))
+        }
+    }
+
+    /// Price received by a swap of `coin_in` for `coin_out`, after the swap fee
+    pub fn price_of(&self, coin_in: Coin, coin_out: Coin) -> Result<f64> {
+        Ok(self.spot_of(coin_in, coin_out)? * (1.0 - self.swap_fee))
+    }
+}
+
+/// Fetches the spot price of a pool along with its asset order and swap fee
+pub async fn fetch_pool_price(pool_id: u64) -> Result<PoolPrice, Box<dyn StdError>> {
     // Get the raw JSON response
-    let raw_json = response.text().await?;
+    let (raw_json, provider) = with_failover(EndpointRole::PoolPrice, |url| async move {
+        HTTP_CLIENT.get(url.replace("{}", pool_id.to_string().as_str())).send().await?.error_for_status()?.text().await
+    }).await?;
+    info!("Pool {} price fetched from {}", pool_id, provider);
+
+    parse_pool_price(&raw_json)
+}
 
+/// Fetches the pool price from every configured provider independently,
+/// providers that fail to answer are left out of the result.
+pub async fn fetch_coin_price_quotes(pool_id: u64) -> Vec<(String, PoolPrice)> {
+    let mut quotes = Vec::new();
+
+    for url in provider_urls(EndpointRole::PoolPrice) {
+        let response = match HTTP_CLIENT.get(url.replace("{}", pool_id.to_string().as_str())).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("!!! Failed to fetch pool {} from {}: {}", pool_id, url, e);
+                continue;
+            }
+        };
+        let raw_json = match response.error_for_status() {
+            Ok(response) => response.text().await,
+            Err(e) => Err(e),
+        };
+        match raw_json.map_err(|e| e.to_string()).and_then(|raw_json| parse_pool_price(&raw_json).map_err(|e| e.to_string())) {
+            Ok(price) => quotes.push((url, price)),
+            Err(e) => warn!("!!! Failed to read pool {} price from {}: {}", pool_id, url, e),
+        }
+    }
+
+    quotes
+}
+
+#[derive(Deserialize, Debug)]
+struct TwapResponse {
+    arithmetic_twap: String,
+}
+
+/// Fetches the arithmetic TWAP of `coin_in` quoted in `coin_out` over the last `window_secs`
+pub async fn fetch_twap_price(twap_url: &str, pool_id: u64, coin_in: Coin, coin_out: Coin, window_secs: i64) -> Result<f64, Box<dyn StdError>> {
+    let start_time = (Utc::now() - ChronoDuration::seconds(window_secs)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let mut url = Url::parse(twap_url)?;
+    url.query_pairs_mut()
+        .append_pair("pool_id", &pool_id.to_string())
+        .append_pair("base_asset", coin_in.denom())
+        .append_pair("quote_asset", coin_out.denom())
+        .append_pair("start_time", &start_time);
+
+    let response = HTTP_CLIENT.get(url).send().await?.error_for_status()?;
+    let twap: TwapResponse = response.json().await?;
+    let price = twap.arithmetic_twap.parse::<f64>()
+        .map_err(|e| format!("Failed to parse arithmetic_twap: {}", e))?;
+
+    Ok(price)
+}
+
+fn parse_pool_price(raw_json: &str) -> Result<PoolPrice, Box<dyn StdError>> {
     // Deserialize only the necessary part
-    let json_data: PoolCommonData = serde_json::from_str(&raw_json)?;
+    let json_data: PoolCommonData = serde_json::from_str(raw_json)?;
 
     // Parse the pool type and get the price
     match json_data.pool.pool_type.as_str() {
         "/osmosis.concentratedliquidity.v1beta1.Pool" => {
-            let json_data: PoolCLData = serde_json::from_str(&raw_json)?;
+            let json_data: PoolCLData = serde_json::from_str(raw_json)?;
    
             // Calculate the price based on the sqrt_price
             let sqrt_price: f64 = json_data.pool.current_sqrt_price.parse()
                 .map_err(|e| format!("Failed to parse sqrt_price: {}", e))?;
-            let mut price = sqrt_price * sqrt_price;
+            let spot = sqrt_price * sqrt_price;
         
-            // Apply spread factor
-            if let Ok(spread_factor) = json_data.pool.spread_factor.parse::<f64>() {
-                price *= 1.0 - spread_factor;
-            } else {
+            // Spread factor, charged on swaps
+            let swap_fee = json_data.pool.spread_factor.parse::<f64>().unwrap_or_else(|_| {
                 eprintln!("Failed to parse spread factor; using price without discount.");
-            }
+                0.0
+            });
             
-            Ok(price)
+            Ok(PoolPrice { base_denom: json_data.pool.token0, quote_denom: json_data.pool.token1, spot, swap_fee })
         },
         "/osmosis.gamm.v1beta1.Pool" => {
-            let json_data: PoolDefaultData = serde_json::from_str(&raw_json)?;
+            let json_data: PoolDefaultData = serde_json::from_str(raw_json)?;
             let pool = json_data.pool;
 
             let swap_fee: f64 = pool.pool_params.swap_fee.parse()
@@ -299,23 +476,14 @@ pub async fn fetch_coin_price(pool_id: u64) -> Result<f64, Box<dyn StdError>> {
                 .map_err(|e| format!("Failed to parse asset_1_weight: {}", e))?;
 
             // Calculate price assuming equal weight
-            let price = (asset_1_amount / asset_1_weight) / (asset_0_amount / asset_0_weight);
-
-            // Apply swap fee
-            let price = price * (1.0 - swap_fee);
+            let spot = (asset_1_amount / asset_1_weight) / (asset_0_amount / asset_0_weight);
 
-            Ok(price)
+            let mut denoms = pool.pool_assets.into_iter().map(|asset| asset.token.denom);
+            let (base_denom, quote_denom) = (denoms.next().unwrap_or_default(), denoms.next().unwrap_or_default());
+            Ok(PoolPrice { base_denom, quote_denom, spot, swap_fee })
             
         },
         _ => Err(format!("Unknown pool type: {}", json_data.pool.pool_type).into())
     }
     
 }
-
-fn get_osmosis_rpc_url() -> String {
-    CONFIG.osmosis_status_url.clone()
-}
-
-fn get_osmosis_pool_price_url() -> String {
-    CONFIG.osmosis_pool_price_url.clone()
-}
\ No newline at end of file
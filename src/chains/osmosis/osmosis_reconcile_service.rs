@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::DateTime;
+use serde_json::{json, Value};
+use log::info;
+use anyhow::{anyhow, Result};
+use crate::chains::coin::Coin;
+use super::osmosis_endpoint_service::{with_failover, EndpointRole, HTTP_CLIENT};
+use super::osmosis_transaction::{account_transactions, append_transaction, fetch_transaction_details, swapped_amounts, update_transaction};
+
+/// Txs fetched per page of the chain history
+const PAGE_SIZE: usize = 100;
+
+/// Seconds after which a broadcasted tx that is not found on chain is marked as timed out
+const STALE_AFTER_SECS: u64 = 600;
+
+/// A difference between the ledger and the chain history
+#[derive(Debug)]
+pub enum Discrepancy {
+    /// a broadcasted or timed out tx that is not on chain
+    NotFound { txhash: String, tx_status: String },
+    /// a broadcasted or timed out tx that could not be looked up, left as is
+    FetchFailed { txhash: String, error: String },
+    /// the ledger and the chain disagree on the code of an executed tx
+    CodeMismatch { txhash: String, ledger: Option<u64>, chain: u64 },
+    /// the ledger and the chain disagree on the amounts of a swap
+    AmountMismatch { txhash: String, ledger: (Option<u64>, Option<u64>), chain: (Option<u64>, Option<u64>) },
+    /// a tx of the account that is not a swap, sent outside the tool
+    Unrecorded { txhash: String, message: String },
+    /// a swap sent outside the tool on a token the tool does not know
+    UnknownDenom { txhash: String, denom: String },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::NotFound { txhash, tx_status } => write!(f, "{} is {} in the ledger and not found on chain", txhash, tx_status),
+            Discrepancy::FetchFailed { txhash, error } => write!(f, "{} could not be looked up: {}", txhash, error),
+            Discrepancy::CodeMismatch { txhash, ledger, chain } => write!(f, "{} has code {:?} in the ledger and {} on chain", txhash, ledger, chain),
+            Discrepancy::AmountMismatch { txhash, ledger, chain } => write!(f, "{} swapped {:?} in the ledger and {:?} on chain", txhash, ledger, chain),
+            Discrepancy::Unrecorded { txhash, message } => write!(f, "{} ({}) was not sent by the tool", txhash, message),
+            Discrepancy::UnknownDenom { txhash, denom } => write!(f, "{} swaps the unknown denom {}, not imported", txhash, denom),
+        }
+    }
+}
+
+/// What reconciling an account changed in its ledger, and what it could not fix
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    /// stale records completed from the chain
+    pub updated: Vec<String>,
+    /// swaps sent outside the tool, added to the ledger
+    pub imported: Vec<String>,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Brings the ledger of an account in line with the chain. Stale records are completed
+/// with their tx details, and the last `limit` txs sent by the account are compared to
+/// the ledger, swaps sent outside the tool are imported. With `dry_run` nothing is written.
+pub async fn reconcile(account_id: &str, limit: usize, dry_run: bool) -> Result<ReconcileReport> {
+    let mut report = ReconcileReport::default();
+    let records = account_transactions(account_id).map_err(|e| anyhow!("Failed to read transactions: {}", e))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+
+    // 1. Complete the records stuck as broadcasted or timed out
+    for tx in records.iter().filter(|tx| tx["tx_status"] == "broadcasted" || tx["tx_status"] == "timeout") {
+        let Some(txhash) = tx["txhash"].as_str() else {
+            continue;
+        };
+        let details = match fetch_transaction_details(txhash, account_id).await {
+            Ok(details) => details,
+            Err(e) => {
+                report.discrepancies.push(Discrepancy::FetchFailed { txhash: txhash.to_string(), error: e.to_string() });
+                continue;
+            }
+        };
+        if details.code.is_some() {
+            if !dry_run {
+                update_transaction(txhash, account_id, details.ledger_fields()).await.map_err(|e| anyhow!("Failed to update tx {}: {}", txhash, e))?;
+            }
+            report.updated.push(txhash.to_string());
+            continue;
+        }
+
+        let tx_status = tx["tx_status"].as_str().unwrap_or_default().to_string();
+        let broadcasted_at = tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()).unwrap_or(now);
+        if tx_status == "broadcasted" && now.saturating_sub(broadcasted_at) > STALE_AFTER_SECS && !dry_run {
+            update_transaction(txhash, account_id, json!({ "tx_status": "timeout" })).await.map_err(|e| anyhow!("Failed to update tx {}: {}", txhash, e))?;
+        }
+        report.discrepancies.push(Discrepancy::NotFound { txhash: txhash.to_string(), tx_status });
+    }
+
+    // 2. Compare the chain history with the ledger
+    let known: HashSet<&str> = records.iter().filter_map(|tx| tx["txhash"].as_str()).collect();
+    for tx_response in fetch_sender_txs(account_id, limit).await? {
+        let Some(txhash) = tx_response["txhash"].as_str() else {
+            continue;
+        };
+        let code = tx_response["code"].as_u64().unwrap_or(0);
+
+        if !known.contains(txhash) {
+            match import_record(&tx_response, account_id) {
+                Ok(record) => {
+                    if !dry_run {
+                        append_transaction(account_id, record).map_err(|e| anyhow!("Failed to import tx {}: {}", txhash, e))?;
+                    }
+                    report.imported.push(txhash.to_string());
+                }
+                Err(discrepancy) => report.discrepancies.push(discrepancy),
+            }
+            continue;
+        }
+
+        // Stale records were completed above, only executed ones are compared
+        let Some(record) = records.iter().find(|tx| tx["txhash"] == txhash && tx["tx_status"] == "executed") else {
+            continue;
+        };
+        let ledger_code = record["status_code"].as_u64();
+        if ledger_code != Some(code) {
+            report.discrepancies.push(Discrepancy::CodeMismatch { txhash: txhash.to_string(), ledger: ledger_code, chain: code });
+            continue;
+        }
+        if code == 0 && record["action"].is_null() {
+            let ledger = (record["tokens_in"].as_u64(), record["tokens_out"].as_u64());
+            let chain = swapped_amounts(&tx_response, account_id);
+            if ledger != chain {
+                report.discrepancies.push(Discrepancy::AmountMismatch { txhash: txhash.to_string(), ledger, chain });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Tx responses of the last `limit` txs sent by the account, newest first
+async fn fetch_sender_txs(account_id: &str, limit: usize) -> Result<Vec<Value>> {
+    let mut tx_responses: Vec<Value> = Vec::new();
+
+    while tx_responses.len() < limit {
+        let offset = tx_responses.len();
+        let (response, provider) = with_failover(EndpointRole::TxSearch, |url| {
+            let url = format!("{}&pagination.limit={}&pagination.offset={}&order_by=ORDER_BY_DESC", url.replace("{}", account_id), PAGE_SIZE, offset);
+            let request = HTTP_CLIENT.get(url).send();
+            async move { request.await?.error_for_status()?.text().await }
+        }).await.map_err(|e| anyhow!("Failed to search the txs of {}: {}", account_id, e))?;
+        info!("Txs {} to {} of {} fetched from {}", offset, offset + PAGE_SIZE, account_id, provider);
+
+        let json: Value = serde_json::from_str(&response)?;
+        let page = json["tx_responses"].as_array().cloned().unwrap_or_default();
+        let last_page = page.len() < PAGE_SIZE;
+        tx_responses.extend(page);
+        if last_page {
+            break;
+        }
+    }
+
+    tx_responses.truncate(limit);
+    Ok(tx_responses)
+}
+
+// Ledger record of a swap sent outside the tool, flagged with `imported`
+fn import_record(tx_response: &Value, account_id: &str) -> Result<Value, Discrepancy> {
+    let txhash = tx_response["txhash"].as_str().unwrap_or_default().to_string();
+    let messages = tx_response["tx"]["body"]["messages"].as_array().cloned().unwrap_or_default();
+    let message_type = messages.first().and_then(|msg| msg["@type"].as_str()).unwrap_or("no message").to_string();
+
+    // Swaps of the gamm or poolmanager module, a single swap per tx
+    let swap = messages.iter().find_map(|msg| {
+        let msg_type = msg["@type"].as_str()?;
+        let routes = msg["routes"].as_array()?;
+        let first_route = routes.first()?;
+        if msg_type.ends_with(".MsgSwapExactAmountIn") {
+            Some(("amount_in", first_route["pool_id"].clone(), msg["token_in"]["denom"].clone(), routes.last()?["token_out_denom"].clone(), msg["token_in"]["amount"].clone()))
+        } else if msg_type.ends_with(".MsgSwapExactAmountOut") {
+            Some(("amount_out", first_route["pool_id"].clone(), first_route["token_in_denom"].clone(), msg["token_out"]["denom"].clone(), msg["token_out"]["amount"].clone()))
+        } else {
+            None
+        }
+    });
+    let Some((swap_type, pool_id, denom_in, denom_out, amount)) = swap else {
+        return Err(Discrepancy::Unrecorded { txhash, message: message_type });
+    };
+
+    let coin = |denom: &Value| {
+        let denom = denom.as_str().unwrap_or_default();
+        Coin::from_denom(denom).ok_or_else(|| Discrepancy::UnknownDenom { txhash: txhash.clone(), denom: denom.to_string() })
+    };
+    let (token_in, token_out) = (coin(&denom_in)?, coin(&denom_out)?);
+
+    let code = tx_response["code"].as_u64().unwrap_or(0);
+    let (tokens_in, tokens_out) = if code == 0 { swapped_amounts(tx_response, account_id) } else { (None, None) };
+    let executed_price = match (tokens_in, tokens_out) {
+        (Some(tokens_in), Some(tokens_out)) if tokens_in > 0 => Some(tokens_out as f64 / tokens_in as f64),
+        _ => None,
+    };
+    let timestamp = tx_response["timestamp"]
+        .as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.timestamp().to_string());
+    let fee = &tx_response["tx"]["auth_info"]["fee"]["amount"][0];
+    let number = |value: &Value| value.as_str().and_then(|v| v.parse::<u64>().ok());
+
+    Ok(json!({
+        "txhash": txhash,
+        "timestamp": timestamp,
+        "status_code": code,
+        "tx_status": "executed",
+        "raw_log": tx_response["raw_log"],
+        "fee_paid": number(&fee["amount"]),
+        "fee_denom": fee["denom"],
+        "pool_id": number(&pool_id),
+        "token_in": token_in,
+        "token_out": token_out,
+        "amount": number(&amount),
+        "swap_type": swap_type,
+        "gas_used": number(&tx_response["gas_used"]),
+        "tokens_in": tokens_in,
+        "tokens_out": tokens_out,
+        "executed_price": executed_price,
+        "imported": true,
+    }))
+}
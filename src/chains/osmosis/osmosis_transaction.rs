@@ -1,13 +1,12 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::thread;
 use std::time::Duration;
-use serde::{Serialize, Deserialize};
+use serde::Serialize;
 use serde_json::{Value, json};
 use anyhow::Error;
-use reqwest::Client;
+use reqwest::StatusCode;
 use crate::config::{CONFIG, get_config_path};
 use crate::chains::coin::Coin;
 use regex::Regex;
@@ -15,131 +14,543 @@ use cosmrs::tx::Tx;
 use prost::Message;
 use crate::utils::format_token_amount_with_denom;
 use log::{info, error, warn, debug};
+use sha2::{Digest, Sha256};
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+use tonic::transport::{ClientTlsConfig, Endpoint};
+use cosmrs::proto::cosmos::tx::v1beta1::service_client::ServiceClient;
+use cosmrs::proto::cosmos::tx::v1beta1::{BroadcastTxRequest, BroadcastMode as ProtoBroadcastMode};
+use tendermint_rpc::{SubscriptionClient, WebSocketClient, Subscription};
+use tendermint_rpc::query::{EventType, Query};
+use crate::config::{BroadcastMode, BroadcastTransport, ConfirmationMethod};
+use crate::chains::osmosis::osmosis_endpoint_service::{with_failover, with_failover_if, EndpointRole, FailoverError, HTTP_CLIENT};
+use crate::chains::osmosis::osmosis_pool_service::{fetch_pool_price, SwapOrder, SwapQuote};
+use crate::trade_outcome::{FailureReason, TradeOutcome};
+use crate::market_maker::MarketSide;
 
+/// How long to wait for a broadcasted tx to be included in a block
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct BroadcastedTx {
+
+/// Ledger record of a broadcasted transaction
+#[derive(Serialize, Debug)]
+struct BroadcastedTx<T: Serialize> {
     txhash: String,
     timestamp: String,
     status_code: Option<u64>,
+    tx_status: String,
+    raw_log: Option<String>,
+    /// set once a provider accepted the tx
+    broadcast_provider: Option<String>,
+    /// fee set in the tx auth info, paid once the tx is included in a block
+    fee_paid: Option<u64>,
+    fee_denom: Option<String>,
+    /// fields specific to the kind of transaction
+    #[serde(flatten)]
+    entry: T,
+}
+
+/// Ledger fields of a swap
+#[derive(Serialize, Debug)]
+struct SwapEntry {
     pool_id: u64,
     token_in: Coin,
     token_out: Coin,
     amount: u64,
     swap_type: String,
     min_price: f64,
-    tx_status: String,
+    /// pool price quoted right before signing
+    quoted_price: Option<f64>,
+    /// worst price accepted by the swap message
+    limit_price: f64,
+    /// min price of the ladder tier this swap fills
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ladder_tier: Option<f64>,
+    /// side of a market making trade
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market_side: Option<MarketSide>,
+    /// set on the swaps of a rebalance
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    rebalance: bool,
+    /// set on swaps that are not trades
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<&'static str>,
+}
+
+/// Outcome of an attempt that never got a tx hash, a skipped trade or a failed broadcast.
+/// These are kept apart from the ledger, which only holds transactions sent to the chain.
+#[derive(Serialize, Debug)]
+struct UnsentAttempt<'a, T: Serialize> {
+    timestamp: String,
+    outcome: &'a TradeOutcome,
+    /// fields of the swap or action that was attempted
+    #[serde(flatten)]
+    entry: &'a T,
+}
+
+/// Node answer to a broadcast request, common to the REST and gRPC transports
+struct BroadcastResponse {
+    txhash: String,
+    code: Option<u64>,
     raw_log: Option<String>,
+    /// URL of the provider that accepted the tx
+    provider: String,
+}
+
+/// Failure of a broadcast, whether the tx may have reached a provider or not
+enum BroadcastError {
+    /// no provider could be reached, the tx was not sent
+    NotSent(anyhow::Error),
+    /// a provider may have received the tx, it can still be included in a block
+    Unconfirmed(anyhow::Error),
+}
+
+impl From<FailoverError> for BroadcastError {
+    fn from(e: FailoverError) -> Self {
+        match e {
+            FailoverError::Exhausted(_) => BroadcastError::NotSent(e.into()),
+            FailoverError::Stopped(_) => BroadcastError::Unconfirmed(e.into()),
+        }
+    }
+}
+
+/// Failure of a gRPC broadcast, the tx can only have been sent once connected
+#[derive(Debug)]
+enum GrpcError {
+    Connect(tonic::transport::Error),
+    Status(tonic::Status),
+}
+
+impl fmt::Display for GrpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrpcError::Connect(e) => write!(f, "failed to connect: {}", e),
+            GrpcError::Status(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+/// Fee set in the auth info of a transaction, in the smallest unit of its denom
+#[derive(Debug, Clone)]
+struct PaidFee {
+    denom: String,
+    amount: u64,
+}
+
+impl PaidFee {
+    /// First fee coin of the transaction, none for a transaction without fee
+    fn of(tx: &Tx) -> Option<Self> {
+        let coin = tx.auth_info.fee.amount.first()?;
+        let amount = coin.amount.to_string().parse().ok()?;
+        Some(PaidFee { denom: coin.denom.to_string(), amount })
+    }
+}
+
+/// Outcome of a tx lookup by hash
+pub struct TxDetails {
+    pub code: Option<u64>,
+    pub raw_log: Option<String>,
+    pub gas_used: Option<u64>,
+    pub tokens_in: Option<u64>,
+    pub tokens_out: Option<u64>,
+    /// URL of the provider that served the lookup
+    pub provider: String,
+}
+
+impl TxDetails {
+    /// Tokens out received per token in spent
+    pub fn executed_price(&self) -> Option<f64> {
+        match (self.tokens_in, self.tokens_out) {
+            (Some(tokens_in), Some(tokens_out)) if tokens_in > 0 => Some(tokens_out as f64 / tokens_in as f64),
+            _ => None,
+        }
+    }
+
+    /// Fields of an executed transaction in the ledger
+    pub fn ledger_fields(&self) -> Value {
+        json!({
+            "tx_status": "executed",
+            "status_code": self.code,
+            "raw_log": self.raw_log,
+            "gas_used": self.gas_used,
+            "tokens_in": self.tokens_in,
+            "tokens_out": self.tokens_out,
+            "executed_price": self.executed_price(),
+            "details_provider": self.provider,
+        })
+    }
+}
+
+/// Live subscription to the CometBFT `Tx` event of a single transaction
+struct TxEventSubscription {
+    client: WebSocketClient,
+    driver: JoinHandle<Result<(), tendermint_rpc::Error>>,
+    subscription: Subscription,
 }
 
 pub async fn broadcast_tx(
     tx: Tx, 
     sender_address: &str, 
-    pool_id: u64, 
-    coin_in: Coin, 
-    coin_out: Coin, 
-    amount: u64, 
-    swap_type: &str,
-    min_price: f64
-) -> Result<bool, anyhow::Error> {
+    order: &SwapOrder,
+    quote: &SwapQuote,
+) -> Result<TradeOutcome, anyhow::Error> {
+    let entry = SwapEntry {
+        pool_id: order.pool_id,
+        token_in: order.coin_in,
+        token_out: order.coin_out,
+        amount: order.amount,
+        swap_type: order.swap_type.to_string(),
+        min_price: order.min_price,
+        quoted_price: quote.quoted_price,
+        limit_price: quote.limit_price,
+        ladder_tier: order.ladder_tier,
+        rebalance: order.rebalance,
+        market_side: order.market_side,
+        action: order.action,
+    };
+    let fee = PaidFee::of(&tx);
+    let outcome = broadcast_and_record(tx, sender_address, &entry).await?;
+
+    // Value the fee in the token received, for the all-in price of the swap
+    if let (TradeOutcome::Executed { txhash, tokens_in: Some(tokens_in), tokens_out: Some(tokens_out) }, Some(fee)) = (&outcome, fee) {
+        if *tokens_in > 0 {
+            match fee_value(&fee, order, *tokens_in, *tokens_out).await {
+                Ok(fee_value) => {
+                    let all_in_price = tokens_out.saturating_sub(fee_value) as f64 / *tokens_in as f64;
+                    let fields = json!({ "fee_value": fee_value, "all_in_price": all_in_price });
+                    if let Err(e) = update_transaction(txhash, sender_address, fields).await {
+                        error!("!!! Failed to store fee value: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("!!! Fee of {} not valued: {}", txhash, e),
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+// Value of the fee in the token out of the swap, from the executed price when the swap
+// trades the fee token, otherwise from the configured price pool of the fee token
+async fn fee_value(fee: &PaidFee, order: &SwapOrder, tokens_in: u64, tokens_out: u64) -> Result<u64, anyhow::Error> {
+    let executed_price = tokens_out as f64 / tokens_in as f64;
+    if fee.denom == order.coin_out.denom() {
+        return Ok(fee.amount);
+    }
+    if fee.denom == order.coin_in.denom() {
+        return Ok((fee.amount as f64 * executed_price) as u64);
+    }
+
+    // Fee token valued at the spot price in the configured token_out, oriented by the pool
+    // asset order, and converted when the swap sells token_out
+    let pool_id = CONFIG.gas_config.price_pool_id.ok_or_else(|| anyhow::anyhow!("gas_config.price_pool_id is not set"))?;
+    let quote = CONFIG.env_constants.token_out;
+    let pool_price = fetch_pool_price(pool_id).await.map_err(|e| anyhow::anyhow!("Failed to fetch fee token price: {}", e))?;
+    let price = pool_price.spot_of(CONFIG.gas_config.token, quote)?;
+    let value_in_quote = fee.amount as f64 * price;
+    if order.coin_out == quote {
+        Ok(value_in_quote as u64)
+    } else if order.coin_in == quote {
+        Ok((value_in_quote * executed_price) as u64)
+    } else {
+        Err(anyhow::anyhow!("Swap trades neither {} nor the fee token", quote))
+    }
+}
+
+/// Broadcasts a transaction that is not a swap, `entry` holds its ledger fields
+/// and must name it with an `action` field
+pub async fn broadcast_action(tx: Tx, sender_address: &str, entry: Value) -> Result<TradeOutcome, anyhow::Error> {
+    broadcast_and_record(tx, sender_address, &entry).await
+}
+
+async fn broadcast_and_record<T: Serialize>(tx: Tx, sender_address: &str, entry: &T) -> Result<TradeOutcome, anyhow::Error> {
+    let fee = PaidFee::of(&tx);
+
     // Encode the transaction
     let proto_tx: cosmrs::proto::cosmos::tx::v1beta1::Tx = tx.into();
     let mut tx_bytes = Vec::new();
     proto_tx.encode(&mut tx_bytes).map_err(|e| anyhow::anyhow!("Failed to encode Tx: {}", e))?;
-    let tx_base64 = base64::encode(&tx_bytes);
+
+    // Subscribe before broadcasting, so the Tx event cannot be missed
+    let txhash = compute_tx_hash(&tx_bytes);
+    let txhash = txhash.as_str();
+    let broadcast_config = &CONFIG.broadcast_config;
+    let subscription = match broadcast_config.confirmation {
+        ConfirmationMethod::Websocket => match subscribe_tx_event(txhash).await {
+            Ok(subscription) => Some(subscription),
+            Err(e) => {
+                warn!("!!! Failed to subscribe to Tx event, falling back to polling: {}", e);
+                None
+            }
+        },
+        ConfirmationMethod::Poll => None,
+    };
+
+    // Store the transaction before broadcasting, a tx that reaches a provider is never missing from the ledger
+    if let Err(e) = store_broadcasted_transaction(sender_address, txhash, fee, entry) {
+        if let Some(subscription) = subscription {
+            close_subscription(subscription);
+        }
+        return Err(anyhow::anyhow!("Failed to store transaction {} before broadcasting: {}", txhash, e));
+    }
 
     // Broadcast the transaction
-    let client = Client::new();
-    let broadcast_url = get_osmosis_broadcast_tx_url();
-    let broadcast_body = json!({
-        "tx_bytes": tx_base64,
-        "mode": "BROADCAST_MODE_ASYNC"
-    });
-    let response = client.post(broadcast_url)
-        .json(&broadcast_body)
-        .send()
-        .await?;
-
-    // Parse the response JSON
-    let response_json: serde_json::Value = match response.json().await {
-        Ok(json) => json,
-        Err(e) => {
-            error!("Failed to parse response JSON: {}", e);
-            return Err(anyhow::anyhow!("Failed to parse response JSON: {}", e));
+    let response = match broadcast_config.transport {
+        BroadcastTransport::Rest => broadcast_tx_rest(tx_bytes, broadcast_config.mode).await,
+        BroadcastTransport::Grpc => broadcast_tx_grpc(tx_bytes, broadcast_config.mode).await,
+    };
+    let check_tx = match response {
+        Ok(response) => {
+            info!(">>> Transaction {} broadcasted through {}", txhash, response.provider);
+            if response.txhash != txhash {
+                warn!("!!! Provider answered with txhash {}, the ledger keeps {}", response.txhash, txhash);
+            }
+            let fields = json!({
+                "broadcast_provider": response.provider,
+                "status_code": response.code,
+                "raw_log": response.raw_log,
+            });
+            if let Err(e) = update_transaction(txhash, sender_address, fields).await {
+                error!("!!! Failed to store broadcast response: {:?}", e);
+            }
+            (response.code, response.raw_log)
+        }
+        // The tx may be in a mempool, it is confirmed like an accepted one so it is never sent twice
+        Err(BroadcastError::Unconfirmed(e)) => {
+            warn!("!!! Broadcast of {} failed after reaching a provider, waiting for it to be included: {}", txhash, e);
+            (Some(0), None)
+        }
+        Err(BroadcastError::NotSent(e)) => {
+            error!("Broadcast failed: {}", e);
+            if let Some(subscription) = subscription {
+                close_subscription(subscription);
+            }
+            if let Err(e) = remove_transaction(txhash, sender_address) {
+                error!("!!! Failed to remove unsent transaction {}: {:?}", txhash, e);
+            }
+            let outcome = TradeOutcome::Failed {
+                txhash: None,
+                reason: FailureReason::Broadcast { error: e.to_string() },
+            };
+            if let Err(e) = record_unsent_outcome(sender_address, entry, &outcome) {
+                error!("!!! Failed to store trade outcome: {:?}", e);
+            }
+            return Ok(outcome);
         }
     };
-    info!(">>> Transaction broadcasted");
 
-    // Store the broadcasted transaction
-    let txhash = match response_json["tx_response"]["txhash"].as_str() {
-        Some(hash) => hash,
-        None => return Err(anyhow::anyhow!("Failed to get txhash from response")),
-    };    
-    let code = match response_json["tx_response"]["code"].as_u64() {
-        Some(code) => Some(code),
-        None => return Err(anyhow::anyhow!("Failed to get code from response")),
-    };
-    let raw_log = response_json["tx_response"]["raw_log"].as_str().map(String::from);
-    let _ = store_broadcasted_transaction(
-        sender_address,
-        txhash,
-        code,
-        raw_log,
-        pool_id,
-        coin_in,
-        coin_out,
-        amount,
-        swap_type,
-        min_price,
-    );
-
-    match code {
-        Some(0) => {           
-            // Poll the transaction status
-            let res = poll_transaction_status(txhash, sender_address).await;
+    let outcome = match check_tx {
+        (Some(0), _) => {
+            // Wait for the transaction to be included in a block
+            let res = match subscription {
+                Some(subscription) => wait_for_tx_event(subscription, txhash, sender_address).await,
+                None => poll_transaction_status(txhash, sender_address).await,
+            };
             match res {
-                Ok(code) => {
-                    match code {
-                        Some(0) => {
-                            info!("Transaction executed successfully");
-                            return Ok(true);
-                        },
-                        Some(err_code) => {
-                            error!("Transaction failed with code: {}", err_code);
-                        },
-                        None => {
-                            error!("Transaction status unknown")
-                        }
+                Ok(Some(details)) if details.code == Some(0) => {
+                    info!("Transaction executed successfully");
+                    TradeOutcome::Executed {
+                        txhash: txhash.to_string(),
+                        tokens_in: details.tokens_in,
+                        tokens_out: details.tokens_out,
+                    }
+                },
+                Ok(Some(details)) => {
+                    let code = details.code.unwrap_or_default();
+                    error!("Transaction failed with code: {}", code);
+                    TradeOutcome::Failed {
+                        txhash: Some(txhash.to_string()),
+                        reason: FailureReason::Execution { code, raw_log: details.raw_log },
                     }
                 },
-                Err(_) => {
-                    error!("Error polling transaction status")
+                Ok(None) => {
+                    error!("Transaction status unknown");
+                    TradeOutcome::Failed { txhash: Some(txhash.to_string()), reason: FailureReason::Timeout }
+                },
+                Err(e) => {
+                    error!("Error polling transaction status: {}", e);
+                    TradeOutcome::Failed { txhash: Some(txhash.to_string()), reason: FailureReason::Timeout }
                 }
             }
         },
-        Some(err_code) => {
-            error!("Broadcast failed with code: {}", err_code);
+        (code, raw_log) => {
+            // In sync mode a non-zero code means the tx was rejected by CheckTx
+            let code = code.unwrap_or_default();
+            error!("Broadcast failed with code: {}, log: {}", code, raw_log.as_deref().unwrap_or(""));
+            if let Some(subscription) = subscription {
+                close_subscription(subscription);
+            }
+            let fields = json!({
+                "tx_status": "error",
+                "status_code": code,
+                "raw_log": raw_log,
+            });
+            if let Err(e) = update_transaction(txhash, sender_address, fields).await {
+                error!("!!! Failed to store CheckTx error: {:?}", e);
+            }
+            TradeOutcome::Failed {
+                txhash: Some(txhash.to_string()),
+                reason: FailureReason::CheckTx { code, raw_log },
+            }
+        }
+    };
+
+    // Persist the outcome along with the transaction
+    if let Err(e) = update_transaction(txhash, sender_address, json!({ "outcome": outcome })).await {
+        error!("!!! Failed to store trade outcome: {:?}", e);
+    }
+
+    Ok(outcome)
+}
+
+/// Broadcasts the tx bytes through the LCD REST endpoint
+async fn broadcast_tx_rest(tx_bytes: Vec<u8>, mode: BroadcastMode) -> Result<BroadcastResponse, BroadcastError> {
+    let broadcast_body = json!({
+        "tx_bytes": base64::encode(&tx_bytes),
+        "mode": match mode {
+            BroadcastMode::Async => "BROADCAST_MODE_ASYNC",
+            BroadcastMode::Sync => "BROADCAST_MODE_SYNC",
+        }
+    });
+
+    // Once the tx reached a provider it may be in its mempool, sending it to the next
+    // provider could submit it twice, so only connection failures are failed over
+    let (response_json, provider) = with_failover_if(EndpointRole::BroadcastTx, |url| {
+        let request = HTTP_CLIENT.post(url).json(&broadcast_body).send();
+        async move { request.await?.error_for_status()?.json::<Value>().await }
+    }, reqwest::Error::is_connect).await?;
+
+    let txhash = match response_json["tx_response"]["txhash"].as_str() {
+        Some(hash) => hash.to_string(),
+        None => return Err(BroadcastError::Unconfirmed(anyhow::anyhow!("Failed to get txhash from response"))),
+    };
+    let code = match response_json["tx_response"]["code"].as_u64() {
+        Some(code) => Some(code),
+        None => return Err(BroadcastError::Unconfirmed(anyhow::anyhow!("Failed to get code from response"))),
+    };
+    let raw_log = response_json["tx_response"]["raw_log"].as_str().map(String::from);
+
+    Ok(BroadcastResponse { txhash, code, raw_log, provider })
+}
+
+/// Broadcasts the tx bytes through the `cosmos.tx.v1beta1.Service` gRPC service
+async fn broadcast_tx_grpc(tx_bytes: Vec<u8>, mode: BroadcastMode) -> Result<BroadcastResponse, BroadcastError> {
+    if CONFIG.broadcast_config.grpc_url.is_empty() {
+        return Err(BroadcastError::NotSent(anyhow::anyhow!("grpc_url must be set to broadcast over gRPC")));
+    }
+    let request = BroadcastTxRequest {
+        tx_bytes,
+        mode: match mode {
+            BroadcastMode::Async => ProtoBroadcastMode::Async as i32,
+            BroadcastMode::Sync => ProtoBroadcastMode::Sync as i32,
         },
-        None => {
-            error!("Broadcast failed with unknown error");
+    };
+
+    // As over REST, only providers that could not be connected to are failed over
+    let (tx_response, provider) = with_failover_if(EndpointRole::GrpcBroadcastTx, |url| {
+        let request = request.clone();
+        async move {
+            let mut endpoint = Endpoint::from_shared(url.clone()).map_err(GrpcError::Connect)?;
+            if url.starts_with("https") {
+                endpoint = endpoint.tls_config(ClientTlsConfig::new()).map_err(GrpcError::Connect)?;
+            }
+            let mut client = ServiceClient::new(endpoint.connect().await.map_err(GrpcError::Connect)?);
+            client.broadcast_tx(request).await.map_err(GrpcError::Status)
+        }
+    }, |e| matches!(e, GrpcError::Connect(_))).await?;
+
+    let tx_response = tx_response
+        .into_inner()
+        .tx_response
+        .ok_or_else(|| BroadcastError::Unconfirmed(anyhow::anyhow!("Failed to get tx_response from gRPC response")))?;
+
+    Ok(BroadcastResponse {
+        txhash: tx_response.txhash,
+        code: Some(tx_response.code as u64),
+        raw_log: Some(tx_response.raw_log),
+        provider,
+    })
+}
+
+/// The tx hash is the uppercase hex SHA-256 of the encoded tx
+fn compute_tx_hash(tx_bytes: &[u8]) -> String {
+    hex::encode_upper(Sha256::digest(tx_bytes))
+}
+
+async fn subscribe_tx_event(txhash: &str) -> Result<TxEventSubscription, anyhow::Error> {
+    let websocket_url = CONFIG.broadcast_config.websocket_url.clone()
+        .ok_or_else(|| anyhow::anyhow!("websocket_url must be set to confirm over websocket"))?;
+
+    let (client, driver) = WebSocketClient::new(websocket_url.as_str()).await?;
+    let driver = tokio::spawn(async move { driver.run().await });
+
+    let query = Query::from(EventType::Tx).and_eq("tx.hash", txhash);
+    let subscription = match client.subscribe(query).await {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            let _ = client.close();
+            return Err(e.into());
+        }
+    };
+
+    Ok(TxEventSubscription { client, driver, subscription })
+}
+
+/// Waits for the Tx event, then fetches the tx details once it has been included in a block
+async fn wait_for_tx_event(mut subscription: TxEventSubscription, txhash: &str, account_id: &str) -> Result<Option<TxDetails>, Box<dyn std::error::Error>> {
+    let event = tokio::time::timeout(CONFIRMATION_TIMEOUT, subscription.subscription.next()).await;
+    close_subscription(subscription);
+
+    match event {
+        Ok(Some(Ok(_))) => {
+            info!("... Tx event received");
+            poll_transaction_status(txhash, account_id).await
+        }
+        Ok(Some(Err(e))) => {
+            warn!("!!! Tx event subscription failed, falling back to polling: {}", e);
+            poll_transaction_status(txhash, account_id).await
+        }
+        Ok(None) => {
+            warn!("!!! Tx event subscription closed, falling back to polling");
+            poll_transaction_status(txhash, account_id).await
+        }
+        Err(_) => {
+            update_transaction_with_timeout(txhash, account_id).await?;
+            warn!("!!! Tx event timed out for txhash: {}", txhash);
+            Ok(None)
         }
     }
+}
 
-    Ok(false)
+fn close_subscription(subscription: TxEventSubscription) {
+    if let Err(e) = subscription.client.close() {
+        debug!("Failed to close websocket client: {}", e);
+    }
+    subscription.driver.abort();
 }
 
-fn store_broadcasted_transaction(
+fn store_broadcasted_transaction<T: Serialize>(
     account_id: &str,
     txhash: &str,
-    status_code: Option<u64>,
-    raw_log: Option<String>,
-    pool_id: u64,
-    token_in: Coin,
-    token_out: Coin,
-    amount: u64,
-    swap_type: &str,
-    min_price: f64
+    fee: Option<PaidFee>,
+    entry: &T,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let tx = BroadcastedTx {
+        txhash: txhash.to_string(),
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs().to_string(),
+        tx_status: "broadcasted".to_string(),
+        status_code: None,
+        raw_log: None,
+        broadcast_provider: None,
+        fee_paid: fee.as_ref().map(|fee| fee.amount),
+        fee_denom: fee.map(|fee| fee.denom),
+        entry,
+    };
+    append_transaction(account_id, serde_json::to_value(tx)?)
+}
+
+/// Adds a transaction record to the account's transaction list
+pub fn append_transaction(account_id: &str, record: Value) -> Result<(), Box<dyn std::error::Error>> {
     let file_path = get_transactions_file_path()?;
     
     // Step 1: Read the file content or create a new file if it doesn't exist
@@ -161,28 +572,68 @@ fn store_broadcasted_transaction(
         .as_array_mut()
         .ok_or("Failed to get account transactions array")?;
 
-    // Step 5: Create the new transaction entry
-    let tx = BroadcastedTx {
-        txhash: txhash.to_string(),
+    // Step 5: Add the new transaction to the account's transaction list
+    account_transactions.push(record);
+
+    // Step 6: Write the updated content back to the file
+    fs::write(file_path, serde_json::to_string(&transactions)?)?;
+
+    Ok(())
+}
+
+/// Removes a transaction record from the account's transaction list, for a tx that was never sent
+fn remove_transaction(txhash: &str, account_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = get_transactions_file_path()?;
+    let mut transactions: Value = serde_json::from_str(&fs::read_to_string(&file_path)?)?;
+
+    if let Some(account_transactions) = transactions.get_mut(account_id).and_then(Value::as_array_mut) {
+        account_transactions.retain(|tx| tx["txhash"] != txhash);
+        fs::write(file_path, serde_json::to_string(&transactions)?)?;
+    }
+    Ok(())
+}
+
+/// Stores the outcome of an attempt that has no ledger record, `entry` holds the fields of the attempted swap
+pub fn record_unsent_outcome<T: Serialize>(account_id: &str, entry: &T, outcome: &TradeOutcome) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = get_unsent_outcomes_file_path()?;
+    let mut outcomes: Value = match fs::read_to_string(&file_path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => json!({}), // No outcomes yet
+    };
+
+    let attempt = UnsentAttempt {
         timestamp: SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs().to_string(),
-        tx_status: "broadcasted".to_string(),
-        status_code: status_code,
-        raw_log,
-        pool_id,
-        token_in,
-        token_out,
-        amount,
-        swap_type: swap_type.to_string(),
-        min_price,
+        outcome,
+        entry,
     };
+    outcomes
+        .as_object_mut()
+        .ok_or("Invalid JSON structure")?
+        .entry(account_id.to_string())
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("Failed to get account outcomes array")?
+        .push(serde_json::to_value(attempt)?);
 
-    // Step 6: Add the new transaction to the account's transaction list
-    account_transactions.push(serde_json::to_value(tx)?);
+    fs::write(file_path, serde_json::to_string(&outcomes)?)?;
+    Ok(())
+}
 
-    // Step 7: Write the updated content back to the file
-    fs::write(file_path, serde_json::to_string(&transactions)?)?;
+/// Outcomes of the attempts without ledger record, for every account
+fn all_unsent_outcomes() -> Result<serde_json::Map<String, Value>, Box<dyn std::error::Error>> {
+    let file_content = match fs::read_to_string(get_unsent_outcomes_file_path()?) {
+        Ok(content) => content,
+        Err(_) => return Ok(serde_json::Map::new()), // No outcomes yet
+    };
 
-    Ok(())
+    match serde_json::from_str(&file_content)? {
+        Value::Object(accounts) => Ok(accounts),
+        _ => Ok(serde_json::Map::new()),
+    }
+}
+
+fn get_unsent_outcomes_file_path() -> Result<PathBuf, Error> {
+    Ok(get_transactions_file_path()?.with_file_name("osmosis_unsent_outcomes.json"))
 }
 
 // Function to get the path to the wallets file
@@ -195,91 +646,83 @@ fn get_transactions_file_path() -> Result<PathBuf, Error> {
   Ok(app_dir_path.join("osmosis_transactions.json"))
 }
 
-pub async fn fetch_transaction_details(txhash: &str, account_id: &str) -> Result<(Option<u64>, Option<String>, Option<u64>, Option<u64>, Option<u64>), Error> {
-    let client = Client::new();
-    let url = get_osmosis_tx_details_url();
-    let url_formated: String = url.replace("{}", txhash); 
-    
-    let response = client.get(&url_formated).send().await?.text().await?;
-    let json: Value = serde_json::from_str(&response)?; 
+pub async fn fetch_transaction_details(txhash: &str, account_id: &str) -> Result<TxDetails, Error> {
+    // A pending tx is answered with a 404, server errors and unreadable bodies are failed over
+    let (json, provider) = with_failover(EndpointRole::TxDetails, |url| {
+        let request = HTTP_CLIENT.get(url.replace("{}", txhash)).send();
+        async move {
+            let response = request.await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Ok(Value::Null),
+                status if status.is_server_error() => Err(anyhow::anyhow!("Server error {}", status)),
+                _ => Ok(serde_json::from_str::<Value>(&response.text().await?)?),
+            }
+        }
+    }).await?;
     let code = json["tx_response"]["code"].as_u64();
     let raw_log = json["tx_response"]["raw_log"].as_str().map(String::from);
     let gas_used = json["tx_response"]["gas_used"].as_str()
         .and_then(|s| s.parse::<u64>().ok());
 
     let (tokens_in, tokens_out) = if code == Some(0) {
-        let events = json["tx_response"]["events"].as_array().unwrap_or(&vec![]).to_vec();
-        let mut tokens_in = None;
-        let mut tokens_out = None;
-
-        for event in events {
-            match event["type"].as_str() {
-                Some("token_swapped") => {
-                    if event["attributes"]
-                        .as_array()
-                        .unwrap_or(&vec![])
-                        .iter()
-                        .any(|attr| attr["key"] == "sender" && attr["value"] == account_id)
-                    {
-                        tokens_in = event["attributes"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .find(|attr| attr["key"] == "tokens_in")
-                            .and_then(|attr| {
-                                attr["value"].as_str().and_then(|s| {
-                                    // Regular expression to match leading digits
-                                    let re = Regex::new(r"^\d+").unwrap();
-                                    re.find(s).and_then(|m| m.as_str().parse::<u64>().ok())
-                                })
-                            });
-
-                        tokens_out = event["attributes"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .find(|attr| attr["key"] == "tokens_out")
-                            .and_then(|attr| {
-                                attr["value"].as_str().and_then(|s| {
-                                    // Regular expression to match leading digits
-                                    let re = Regex::new(r"^\d+").unwrap();
-                                    re.find(s).and_then(|m| m.as_str().parse::<u64>().ok())
-                                })
-                            });
-                    }
-                },
-                _ => {}
-            }
-        }        
-
-        (tokens_in, tokens_out)
+        swapped_amounts(&json["tx_response"], account_id)
     } else {
         (None, None)
     };
 
-    Ok((code, raw_log, gas_used, tokens_in, tokens_out))
+    Ok(TxDetails { code, raw_log, gas_used, tokens_in, tokens_out, provider })
 }
 
-async fn poll_transaction_status(txhash: &str, account_id: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+/// Amounts in and out of the last `token_swapped` event of the account in a tx response
+pub fn swapped_amounts(tx_response: &Value, account_id: &str) -> (Option<u64>, Option<u64>) {
+    let events = tx_response["events"].as_array().unwrap_or(&vec![]).to_vec();
+    let mut tokens_in = None;
+    let mut tokens_out = None;
+
+    for event in events {
+        if event["type"].as_str() != Some("token_swapped") {
+            continue;
+        }
+        let attributes = event["attributes"].as_array().cloned().unwrap_or_default();
+        if attributes.iter().any(|attr| attr["key"] == "sender" && attr["value"] == account_id) {
+            tokens_in = parse_event_amount(&attributes, "tokens_in");
+            tokens_out = parse_event_amount(&attributes, "tokens_out");
+        }
+    }
+
+    (tokens_in, tokens_out)
+}
+
+// Extracts the amount of an event attribute formatted as "<amount><denom>"
+fn parse_event_amount(attributes: &[Value], key: &str) -> Option<u64> {
+    // Regular expression to match leading digits
+    let re = Regex::new(r"^\d+").unwrap();
+    attributes
+        .iter()
+        .find(|attr| attr["key"] == key)
+        .and_then(|attr| attr["value"].as_str())
+        .and_then(|s| re.find(s).and_then(|m| m.as_str().parse::<u64>().ok()))
+}
+
+async fn poll_transaction_status(txhash: &str, account_id: &str) -> Result<Option<TxDetails>, Box<dyn std::error::Error>> {
     let start_time = std::time::SystemTime::now();
-    let timeout_duration = Duration::new(60, 0); // 60 seconds
     let poll_interval = Duration::new(3, 0); // 3 seconds
 
     loop {
         let elapsed = start_time.elapsed()?;
-        if elapsed >= timeout_duration {
-            update_transaction_with_timeout(txhash).await?;
+        if elapsed >= CONFIRMATION_TIMEOUT {
+            update_transaction_with_timeout(txhash, account_id).await?;
             warn!("!!! Transaction polling timed out for txhash: {}", txhash);
             return Ok(None);
         }
 
         // Fetch transaction details
         match fetch_transaction_details(txhash, account_id).await {
-            Ok((code, raw_log, gas_used, tokens_in, tokens_out)) => {
-                if code.is_some() {
+            Ok(details) => {
+                if details.code.is_some() {
                     // Transaction was executed
-                    update_transaction(txhash, account_id, "executed", code, raw_log, gas_used, tokens_in, tokens_out).await?;
-                    return Ok(code);
+                    update_transaction(txhash, account_id, details.ledger_fields()).await?;
+                    return Ok(Some(details));
                 } else {
                     info!("... Transaction not yet confirmed");
                 }
@@ -291,21 +734,17 @@ async fn poll_transaction_status(txhash: &str, account_id: &str) -> Result<Optio
         }
 
         // Wait before the next polling attempt
-        thread::sleep(poll_interval);
+        tokio::time::sleep(poll_interval).await;
     }
 }
 
 
-// Function to update the transaction details in the JSON file
-async fn update_transaction(
+// Function to update the transaction details in the JSON file,
+// every key of `fields` is written to the transaction entry
+pub async fn update_transaction(
     txhash: &str,
     account_id: &str,
-    status: &str,
-    code: Option<u64>,
-    raw_log: Option<String>,
-    gas_used: Option<u64>,
-    tokens_in: Option<u64>,
-    tokens_out: Option<u64>,
+    fields: Value,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let file_path = get_transactions_file_path()?;
     
@@ -319,12 +758,11 @@ async fn update_transaction(
             .and_then(|array| array.iter_mut().find(|tx| tx["txhash"] == txhash)) {
 
             // Update the transaction details
-            transaction["tx_status"] = json!(status);
-            transaction["status_code"] = json!(code);
-            transaction["raw_log"] = json!(raw_log);
-            transaction["gas_used"] = json!(gas_used);
-            transaction["tokens_in"] = json!(tokens_in);
-            transaction["tokens_out"] = json!(tokens_out);
+            if let Some(fields) = fields.as_object() {
+                for (key, value) in fields {
+                    transaction[key] = value.clone();
+                }
+            }
 
             debug!("update_transaction: {}", transaction);
 
@@ -337,16 +775,125 @@ async fn update_transaction(
 }
 
 // Function to handle timeout scenario
-async fn update_transaction_with_timeout(txhash: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // Implement your logic to update the transaction with timeout error here
-    update_transaction(txhash, "account_id", "timeout", None, None, None, None, None).await?;
+async fn update_transaction_with_timeout(txhash: &str, account_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = json!({
+        "tx_status": "timeout",
+        "status_code": null,
+        "raw_log": null,
+        "gas_used": null,
+        "tokens_in": null,
+        "tokens_out": null,
+    });
+    update_transaction(txhash, account_id, fields).await?;
     Ok(())
 }
 
-pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
+/// Transactions stored for an account, empty when there are none
+pub fn account_transactions(account_id: &str) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    let file_path = get_transactions_file_path()?;
+    let file_content = match fs::read_to_string(&file_path) {
+        Ok(content) => content,
+        Err(_) => return Ok(Vec::new()), // No trades yet
+    };
+    let mut transactions: Value = serde_json::from_str(&file_content)?;
+
+    match transactions[account_id].take() {
+        Value::Array(txs) => Ok(txs),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Transactions stored for every account, empty when there are none
+pub fn all_transactions() -> Result<serde_json::Map<String, Value>, Box<dyn std::error::Error>> {
+    let file_path = get_transactions_file_path()?;
+    let file_content = match fs::read_to_string(&file_path) {
+        Ok(content) => content,
+        Err(_) => return Ok(serde_json::Map::new()), // No trades yet
+    };
+
+    match serde_json::from_str(&file_content)? {
+        Value::Object(accounts) => Ok(accounts),
+        _ => Ok(serde_json::Map::new()),
+    }
+}
+
+/// Sum of the order amounts of the swaps streamed by an account from `token_in` to `token_out`
+/// on a pool since the given unix time, in microns. Market making and rebalance swaps, and
+/// swaps imported from the chain are not part of a stream and are left out.
+pub fn executed_amount_since(account_id: &str, pool_id: u64, token_in: Coin, token_out: Coin, swap_type: &str, since: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    let coin = |value: &Value| value.as_str().and_then(|denom| denom.parse::<Coin>().ok());
+    let total = account_transactions(account_id)?
+        .iter()
+        .filter(|tx| tx["tx_status"] == "executed" && tx["status_code"].as_u64() == Some(0))
+        .filter(|tx| tx["pool_id"].as_u64() == Some(pool_id) && tx["swap_type"] == swap_type && tx["action"].is_null())
+        .filter(|tx| coin(&tx["token_in"]) == Some(token_in) && coin(&tx["token_out"]) == Some(token_out))
+        .filter(|tx| tx["market_side"].is_null() && tx["rebalance"] != true && tx["imported"] != true)
+        .filter(|tx| tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()).is_some_and(|t| t >= since))
+        .filter_map(|tx| tx["amount"].as_u64())
+        .sum();
+
+    Ok(total)
+}
+
+/// Pools and share denoms the account joined, from its executed `lp_join` records
+pub fn joined_pools(account_id: &str) -> Result<Vec<(u64, String)>, Box<dyn std::error::Error>> {
+    let mut pools: Vec<(u64, String)> = Vec::new();
+    for tx in account_transactions(account_id)? {
+        if tx["action"] != "lp_join" || tx["tx_status"] != "executed" || tx["status_code"].as_u64() != Some(0) {
+            continue;
+        }
+        if let (Some(pool_id), Some(share_denom)) = (tx["pool_id"].as_u64(), tx["share_denom"].as_str()) {
+            if !pools.iter().any(|(id, _)| *id == pool_id) {
+                pools.push((pool_id, share_denom.to_string()));
+            }
+        }
+    }
+
+    Ok(pools)
+}
+
+// Label of a stored `TradeOutcome`: "executed" or the kind of its skip/failure reason
+fn outcome_label(outcome: &Value) -> Option<&str> {
+    match outcome["kind"].as_str()? {
+        "executed" => Some("executed"),
+        _ => outcome["reason"]["kind"].as_str(),
+    }
+}
+
+// Summary of a pool before any transaction is counted
+fn empty_pool_summary(pool_id: u64, token_in: &str, token_out: &str) -> Value {
+    json!({
+        "pool_id": pool_id,
+        "token_in": token_in,
+        "token_out": token_out,
+        "tx_total_count": 0,
+        "tx_success_count": 0,
+        "tx_failed_count": 0,
+        "total_tokens_in": 0,
+        "total_tokens_out": 0,
+        "total_gas_used": 0,
+        "total_fees_paid": 0,
+        "total_fee_value": 0,
+        "swap_amount_in_count": 0,
+        "swap_amount_out_count": 0,
+        "outcomes": {},
+    })
+}
+
+/// Totals per account and pool of the transactions broadcasted from `from` and before `to`, in unix seconds.
+/// Outcomes also count the skipped trades and failed broadcasts, which have no ledger record.
+pub fn summarize_transactions(from: Option<u64>, to: Option<u64>) -> Result<Value, Box<dyn std::error::Error>> {
     let file_path = get_transactions_file_path()?;
     let file_content = fs::read_to_string(&file_path)?;
-    let transactions: Value = serde_json::from_str(&file_content)?;
+    let mut transactions: Value = serde_json::from_str(&file_content)?;
+    let unsent_outcomes = all_unsent_outcomes()?;
+
+    // Accounts whose attempts were all skipped have no ledger record yet
+    if let Some(account_map) = transactions.as_object_mut() {
+        for account_id in unsent_outcomes.keys() {
+            account_map.entry(account_id.clone()).or_insert_with(|| json!([]));
+        }
+    }
 
     let mut summary = serde_json::Map::new();
 
@@ -358,6 +905,14 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
             if let Some(transactions) = tx_list.as_array() {
                 // Iterate through each transaction
                 for tx in transactions {
+                    // Liquidity and transfer actions are not swaps
+                    if !tx["action"].is_null() {
+                        continue;
+                    }
+                    let timestamp = tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok());
+                    if from.is_some_and(|from| timestamp.is_none_or(|t| t < from)) || to.is_some_and(|to| timestamp.is_none_or(|t| t >= to)) {
+                        continue;
+                    }
                     let pool_id = tx["pool_id"].as_u64().unwrap_or(0);
                     let token_in = tx["token_in"].as_str().unwrap_or("unknown");
                     let token_out = tx["token_out"].as_str().unwrap_or("unknown");
@@ -365,6 +920,8 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
                     let tokens_in = tx["tokens_in"].as_u64().unwrap_or(0);
                     let tokens_out = tx["tokens_out"].as_u64().unwrap_or(0);
                     let gas_used = tx["gas_used"].as_u64().unwrap_or(0);
+                    let fee_paid = tx["fee_paid"].as_u64().unwrap_or(0);
+                    let fee_value = tx["fee_value"].as_u64().unwrap_or(0);
                     let swap_type = tx["swap_type"].as_str().unwrap_or("unknown");
 
                     // Create a unique key for each combination of pool_id, token_in, and token_out
@@ -372,19 +929,7 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
 
                     // Initialize the summary for this pool if it doesn't exist
                     let pool_summary = pool_summaries.entry(pool_key.clone())
-                        .or_insert_with(|| json!({
-                            "pool_id": pool_id,
-                            "token_in": token_in,
-                            "token_out": token_out,
-                            "tx_total_count": 0,
-                            "tx_success_count": 0,
-                            "tx_failed_count": 0,
-                            "total_tokens_in": 0,
-                            "total_tokens_out": 0,
-                            "total_gas_used": 0,
-                            "swap_amount_in_count": 0,
-                            "swap_amount_out_count": 0,                            
-                        }));
+                        .or_insert_with(|| empty_pool_summary(pool_id, token_in, token_out));
 
                     // Update the counts and totals based on transaction status and type
                     if let Some(pool_summary_obj) = pool_summary.as_object_mut() {
@@ -394,6 +939,17 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
                             _ => {}
                         }
 
+                        // Count outcomes by their reason, records older than the outcome field are not counted
+                        if let Some(label) = outcome_label(&tx["outcome"]) {
+                            let outcomes = pool_summary_obj.get_mut("outcomes").unwrap();
+                            outcomes[label] = json!(outcomes[label].as_u64().unwrap_or(0) + 1);
+                        }
+
+                        // Every tx included in a block paid its fee, whatever its code
+                        if tx["tx_status"] == "executed" {
+                            *pool_summary_obj.get_mut("total_fees_paid").unwrap() = json!(pool_summary_obj["total_fees_paid"].as_u64().unwrap() + fee_paid);
+                        }
+
                         match status_code {
                             0 => {
                                 *pool_summary_obj.get_mut("tx_total_count").unwrap() = json!(pool_summary_obj["tx_total_count"].as_u64().unwrap() + 1);
@@ -401,6 +957,18 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
                                 *pool_summary_obj.get_mut("total_tokens_in").unwrap() = json!(pool_summary_obj["total_tokens_in"].as_u64().unwrap() + tokens_in);
                                 *pool_summary_obj.get_mut("total_tokens_out").unwrap() = json!(pool_summary_obj["total_tokens_out"].as_u64().unwrap() + tokens_out);
                                 *pool_summary_obj.get_mut("total_gas_used").unwrap() = json!(pool_summary_obj["total_gas_used"].as_u64().unwrap() + gas_used); // Add gas used
+                                *pool_summary_obj.get_mut("total_fee_value").unwrap() = json!(pool_summary_obj["total_fee_value"].as_u64().unwrap() + fee_value);
+
+                                // Progress of each ladder tier
+                                if let Some(tier) = tx["ladder_tier"].as_f64() {
+                                    let tiers = pool_summary_obj.entry("ladder_tiers").or_insert_with(|| json!({}));
+                                    let tier_summary = tiers.as_object_mut().unwrap()
+                                        .entry(tier.to_string())
+                                        .or_insert_with(|| json!({ "tx_success_count": 0, "total_tokens_in": 0, "total_tokens_out": 0 }));
+                                    tier_summary["tx_success_count"] = json!(tier_summary["tx_success_count"].as_u64().unwrap() + 1);
+                                    tier_summary["total_tokens_in"] = json!(tier_summary["total_tokens_in"].as_u64().unwrap() + tokens_in);
+                                    tier_summary["total_tokens_out"] = json!(tier_summary["total_tokens_out"].as_u64().unwrap() + tokens_out);
+                                }
                             },
                             _ => *pool_summary_obj.get_mut("tx_failed_count").unwrap() = json!(pool_summary_obj["tx_failed_count"].as_u64().unwrap() + 1),
                         }
@@ -408,20 +976,53 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
                 }
             }
 
+            for attempt in unsent_outcomes.get(account_id).and_then(Value::as_array).into_iter().flatten() {
+                let timestamp = attempt["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok());
+                if !attempt["action"].is_null()
+                    || from.is_some_and(|from| timestamp.is_none_or(|t| t < from))
+                    || to.is_some_and(|to| timestamp.is_none_or(|t| t >= to)) {
+                    continue;
+                }
+                let Some(label) = outcome_label(&attempt["outcome"]) else {
+                    continue;
+                };
+                let pool_id = attempt["pool_id"].as_u64().unwrap_or(0);
+                let token_in = attempt["token_in"].as_str().unwrap_or("unknown");
+                let token_out = attempt["token_out"].as_str().unwrap_or("unknown");
+                let pool_summary = pool_summaries.entry(format!("{}-{}-{}", pool_id, token_in, token_out))
+                    .or_insert_with(|| empty_pool_summary(pool_id, token_in, token_out));
+                let outcomes = &mut pool_summary["outcomes"];
+                outcomes[label] = json!(outcomes[label].as_u64().unwrap_or(0) + 1);
+            }
+
             // Calculate final statistics for each pool
             for (_, pool_summary) in pool_summaries.iter_mut() {
                 if let Some(pool_summary_obj) = pool_summary.as_object_mut() {
                     let executed_transactions = pool_summary_obj["tx_total_count"].as_u64().unwrap_or(0);
-                    let average_price = if executed_transactions > 0 {
-                        pool_summary_obj["total_tokens_out"].as_f64().unwrap() / pool_summary_obj["total_tokens_in"].as_f64().unwrap()
+                    let total_tokens_out = pool_summary_obj["total_tokens_out"].as_u64().unwrap_or(0);
+                    let total_fee_value = pool_summary_obj["total_fee_value"].as_u64().unwrap_or(0);
+                    let (average_price, all_in_price) = if executed_transactions > 0 {
+                        let total_tokens_in = pool_summary_obj["total_tokens_in"].as_f64().unwrap();
+                        (total_tokens_out as f64 / total_tokens_in, total_tokens_out.saturating_sub(total_fee_value) as f64 / total_tokens_in)
                     } else {
-                        0.0
+                        (0.0, 0.0)
                     };
 
                     pool_summary_obj.insert("average_price".to_string(), json!(format_token_amount_with_denom((average_price * 1_000_000f64) as u64, pool_summary_obj["token_out"].as_str().unwrap_or("unknown"))));
+                    pool_summary_obj.insert("all_in_price".to_string(), json!(format_token_amount_with_denom((all_in_price * 1_000_000f64) as u64, pool_summary_obj["token_out"].as_str().unwrap_or("unknown"))));
                     pool_summary_obj.insert("total_tokens_in".to_string(), json!(format_token_amount_with_denom(pool_summary_obj["total_tokens_in"].as_u64().unwrap_or(0), pool_summary_obj["token_in"].as_str().unwrap_or("unknown"))));
                     pool_summary_obj.insert("total_tokens_out".to_string(), json!(format_token_amount_with_denom(pool_summary_obj["total_tokens_out"].as_u64().unwrap_or(0), pool_summary_obj["token_out"].as_str().unwrap_or("unknown"))));
-                    pool_summary_obj.insert("total_gas_used".to_string(), json!(format_token_amount_with_denom(pool_summary_obj["total_gas_used"].as_u64().unwrap_or(0), Coin::OSMO.to_string().as_str())));
+                    pool_summary_obj.insert("total_fees_paid".to_string(), json!(format_token_amount_with_denom(pool_summary_obj["total_fees_paid"].as_u64().unwrap_or(0), CONFIG.gas_config.token.to_string().as_str())));
+                    pool_summary_obj.insert("total_fee_value".to_string(), json!(format_token_amount_with_denom(total_fee_value, pool_summary_obj["token_out"].as_str().unwrap_or("unknown"))));
+
+                    let token_in = pool_summary_obj["token_in"].as_str().unwrap_or("unknown").to_string();
+                    let token_out = pool_summary_obj["token_out"].as_str().unwrap_or("unknown").to_string();
+                    if let Some(tiers) = pool_summary_obj.get_mut("ladder_tiers").and_then(Value::as_object_mut) {
+                        for tier_summary in tiers.values_mut() {
+                            tier_summary["total_tokens_in"] = json!(format_token_amount_with_denom(tier_summary["total_tokens_in"].as_u64().unwrap_or(0), &token_in));
+                            tier_summary["total_tokens_out"] = json!(format_token_amount_with_denom(tier_summary["total_tokens_out"].as_u64().unwrap_or(0), &token_out));
+                        }
+                    }
                 }
             }
 
@@ -432,13 +1033,3 @@ pub fn summarize_transactions() -> Result<Value, Box<dyn std::error::Error>> {
     Ok(Value::Object(summary))
 }
 
-
-
-
-fn get_osmosis_broadcast_tx_url() -> String {
-    CONFIG.osmosis_broadcast_tx_url.clone()
-}
-
-fn get_osmosis_tx_details_url() -> String {
-    CONFIG.osmosis_tx_details_url.clone()
-}
\ No newline at end of file
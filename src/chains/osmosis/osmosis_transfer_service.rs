@@ -0,0 +1,133 @@
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::json;
+use cosmrs::{AccountId, Any};
+use cosmrs::proto::cosmos::bank::v1beta1::MsgSend;
+use cosmrs::proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+use cosmrs::proto::ibc::applications::transfer::v1::MsgTransfer;
+use cosmrs::proto::ibc::core::client::v1::Height as IbcHeight;
+use prost::Message;
+use anyhow::{anyhow, Result};
+use crate::chains::coin::CoinAmount;
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::trade_outcome::TradeOutcome;
+use super::osmosis_tx_builder::TxBuilder;
+
+/// Bech32 prefix of Osmosis addresses, the only destination of a transfer without an IBC route
+const OSMOSIS_PREFIX: &str = "osmo";
+
+/// Transfer of a single token to another account, on Osmosis or over IBC
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub to_address: String,
+    pub token: CoinAmount,
+    pub memo: String,
+    /// IBC route, none for a transfer on Osmosis
+    pub ibc: Option<IbcRoute>,
+}
+
+/// Channel and timeouts of an IBC transfer, at least one timeout must be set
+#[derive(Debug, Clone)]
+pub struct IbcRoute {
+    pub channel: String,
+    /// revision number and height on the destination chain
+    pub timeout_height: Option<(u64, u64)>,
+    /// seconds from now before the transfer expires
+    pub timeout_secs: Option<u64>,
+}
+
+impl Transfer {
+    /// Name of the transfer in the ledger
+    pub fn action(&self) -> &'static str {
+        match self.ibc {
+            Some(_) => "ibc_transfer",
+            None => "send",
+        }
+    }
+
+    /// Fails on an invalid destination address or missing IBC timeout
+    pub fn validate(&self) -> Result<()> {
+        let to_address = AccountId::from_str(&self.to_address).map_err(|e| anyhow!("Invalid destination address {}: {}", self.to_address, e))?;
+        match &self.ibc {
+            None if to_address.prefix() != OSMOSIS_PREFIX => {
+                return Err(anyhow!("Destination address {} is not an Osmosis address, use an IBC transfer for other chains", self.to_address));
+            }
+            Some(route) if route.timeout_height.is_none() && route.timeout_secs.is_none() => {
+                return Err(anyhow!("An IBC transfer needs a timeout height or timestamp"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parses an IBC timeout height, `<revision number>-<revision height>` e.g. `1-1000000`
+pub fn parse_timeout_height(input: &str) -> Result<(u64, u64), String> {
+    let (number, height) = input
+        .split_once('-')
+        .ok_or_else(|| format!("Invalid timeout height '{}', expected <revision number>-<revision height>", input))?;
+    let number = number.parse().map_err(|e| format!("Invalid revision number in '{}': {}", input, e))?;
+    let height = height.parse().map_err(|e| format!("Invalid revision height in '{}': {}", input, e))?;
+    Ok((number, height))
+}
+
+/// Signs and broadcasts the transfer, recorded in the ledger under its action
+pub async fn transfer(signer: &Signer, transfer: &Transfer) -> Result<TradeOutcome> {
+    transfer_as(signer, transfer, transfer.action()).await
+}
+
+/// Same as `transfer`, recorded in the ledger under the given action
+pub async fn transfer_as(signer: &Signer, transfer: &Transfer, action: &str) -> Result<TradeOutcome> {
+    transfer.validate()?;
+    let sender = signer.get_account_address();
+    let token = ProtoCoin {
+        denom: transfer.token.coin.denom().to_string(),
+        amount: transfer.token.amount.to_string(),
+    };
+
+    let mut entry = json!({
+        "action": action,
+        "to_address": transfer.to_address,
+        "token": transfer.token.coin,
+        "amount": transfer.token.amount,
+    });
+
+    // Step 1. Create the message
+    let msg = match &transfer.ibc {
+        None => {
+            let msg = MsgSend {
+                from_address: sender.to_string(),
+                to_address: transfer.to_address.clone(),
+                amount: vec![token],
+            };
+            Any { type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(), value: msg.encode_to_vec() }
+        }
+        Some(route) => {
+            let timeout_timestamp = match route.timeout_secs {
+                Some(secs) => {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
+                    (now.as_secs() + secs) * 1_000_000_000
+                }
+                None => 0,
+            };
+            let msg = MsgTransfer {
+                source_port: "transfer".to_string(),
+                source_channel: route.channel.clone(),
+                token: Some(token),
+                sender: sender.to_string(),
+                receiver: transfer.to_address.clone(),
+                timeout_height: route.timeout_height.map(|(revision_number, revision_height)| IbcHeight { revision_number, revision_height }),
+                timeout_timestamp,
+            };
+            entry["ibc"] = json!({
+                "channel": route.channel,
+                "timeout_height": route.timeout_height.map(|(number, height)| format!("{}-{}", number, height)),
+                "timeout_timestamp": timeout_timestamp,
+            });
+            Any { type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(), value: msg.encode_to_vec() }
+        }
+    };
+
+    // Step 2. Sign and broadcast
+    TxBuilder::new(signer).message(msg).memo(&transfer.memo).broadcast(entry).await
+}
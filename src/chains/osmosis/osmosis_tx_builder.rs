@@ -0,0 +1,181 @@
+use serde_json::Value;
+use rand::Rng;
+use anyhow::{anyhow, Result};
+use cosmrs::tendermint::{block::Height, chain::Id};
+use cosmrs::tx::{AuthInfo, Body, Fee, SignDoc, Tx};
+use cosmrs::Any;
+use cosmrs::Coin as CosmosCoin;
+use cosmrs::Decimal;
+use crate::config::CONFIG;
+use crate::chains::chain::ChainType;
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_account_service::fetch_account_info;
+use crate::trade_outcome::TradeOutcome;
+use super::osmosis_pool_service::get_current_block_height;
+use super::osmosis_transaction::broadcast_action;
+
+/// Blocks after the current height before an unconfirmed transaction expires
+const DEFAULT_TIMEOUT_BLOCKS: u64 = 200;
+
+/// How the fee of a transaction is set, always paid in uosmo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeStrategy {
+    /// the configured gas config
+    Fixed,
+    /// the configured gas config raised by a random share of the footprint gas jitter
+    Jittered,
+}
+
+impl FeeStrategy {
+    /// Fee amount and gas limit, the jittered fee is never below the configured one
+    pub fn resolve(&self) -> (u64, u64) {
+        match *self {
+            FeeStrategy::Fixed => (CONFIG.gas_config.amount, CONFIG.gas_config.gas_limit),
+            FeeStrategy::Jittered => {
+                let jitter_pct = CONFIG.footprint.gas_jitter_pct;
+                if jitter_pct <= 0.0 {
+                    return FeeStrategy::Fixed.resolve();
+                }
+                let factor = 1.0 + rand::thread_rng().gen_range(0.0..=jitter_pct) / 100.0;
+                (
+                    (CONFIG.gas_config.amount as f64 * factor) as u64,
+                    (CONFIG.gas_config.gas_limit as f64 * factor) as u64,
+                )
+            }
+        }
+    }
+
+    /// Highest fee amount `resolve` can return, the gas balance a transaction needs
+    pub fn max_amount(&self) -> u64 {
+        let jitter_pct = match *self {
+            FeeStrategy::Fixed => 0.0,
+            FeeStrategy::Jittered => CONFIG.footprint.gas_jitter_pct.max(0.0),
+        };
+        (CONFIG.gas_config.amount as f64 * (1.0 + jitter_pct / 100.0)) as u64
+    }
+}
+
+/// Builds, signs and broadcasts a transaction of arbitrary messages.
+/// Defaults to an empty memo, a timeout 200 blocks ahead and the fixed fee.
+pub struct TxBuilder<'a> {
+    signer: &'a Signer,
+    messages: Vec<Any>,
+    memo: String,
+    timeout_blocks: u64,
+    fee: FeeStrategy,
+}
+
+impl<'a> TxBuilder<'a> {
+    pub fn new(signer: &'a Signer) -> Self {
+        TxBuilder {
+            signer,
+            messages: Vec::new(),
+            memo: String::new(),
+            timeout_blocks: DEFAULT_TIMEOUT_BLOCKS,
+            fee: FeeStrategy::Fixed,
+        }
+    }
+
+    pub fn message(mut self, message: Any) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    pub fn messages(mut self, messages: Vec<Any>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    pub fn memo(mut self, memo: &str) -> Self {
+        self.memo = memo.to_string();
+        self
+    }
+
+    pub fn fee(mut self, fee: FeeStrategy) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Signs against the current chain state
+    pub async fn sign(&self) -> Result<Tx> {
+        // Step 1. Get the current block height
+        let current_height = get_current_block_height().await.map_err(|e| anyhow!("Failed to get current block height: {}", e))?;
+
+        // Step 2. Fetch account number and sequence
+        let (account_number, sequence) = fetch_account_info(self.signer.get_account_address()).await.map_err(|e| anyhow!("Failed to fetch account info: {}", e))?;
+
+        self.sign_at(current_height + self.timeout_blocks, account_number, sequence)
+    }
+
+    /// Signs with the given chain state, without network access. With a fixed fee
+    /// the sign bytes only depend on the builder and the arguments.
+    pub fn sign_at(&self, timeout_height: u64, account_number: u64, sequence: u64) -> Result<Tx> {
+        // Step 1. Create TxBody
+        let tx_body = Body {
+            messages: self.messages.clone(),
+            memo: self.memo.clone(),
+            timeout_height: Height::try_from(timeout_height)?,
+            extension_options: vec![],
+            non_critical_extension_options: vec![],
+        };
+
+        // Step 2. Create AuthInfo with fee details
+        let (fee_amount, gas_limit) = self.fee.resolve();
+        let fee = Fee::from_amount_and_gas(CosmosCoin {
+            denom: "uosmo".parse().unwrap(),
+            amount: Decimal::from(fee_amount),
+        }, gas_limit);
+        let auth_info = AuthInfo {
+            signer_infos: vec![self.signer.create_signer_info(sequence)],
+            fee,
+        };
+
+        // Step 3. Create and sign the doc
+        let chain_id = Id::try_from(ChainType::Osmosis.chain_id().clone())?;
+        let sign_doc = SignDoc::new(&tx_body, &auth_info, &chain_id, account_number).map_err(|e| anyhow!("Failed to create SignDoc: {}", e))?;
+        let tx_bytes = self.signer.sign_doc(sign_doc).map_err(|e| anyhow!("Failed to sign the transaction: {}", e))?;
+
+        // Step 4. Parse the signed transaction
+        Tx::from_bytes(&tx_bytes).map_err(|e| anyhow!("Failed to parse transaction bytes: {}", e))
+    }
+
+    /// Signs and broadcasts, `entry` holds the ledger fields and must name the transaction with an `action` field
+    pub async fn broadcast(&self, entry: Value) -> Result<TradeOutcome> {
+        let tx = self.sign().await?;
+        broadcast_action(tx, self.signer.get_account_address(), entry).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmrs::proto::cosmos::bank::v1beta1::MsgSend;
+    use cosmrs::proto::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use prost::Message;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    // Sign bytes of a MsgSend of 1 OSMO to the signer itself, with the prod chain id and gas config
+    const SIGN_DOC_HEX: &str = "0a9b010a8c010a1c2f636f736d6f732e62616e6b2e763162657461312e4d736753656e64126c0a2b6f736d6f3139726c34636d32686d7238616679346b6c6470787a33666b61346a6775713061356d37646638122b6f736d6f3139726c34636d32686d7238616679346b6c6470787a33666b61346a6775713061356d376466381a100a05756f736d6f1207313030303030301206676f6c64656e1888863d12680a500a460a1f2f636f736d6f732e63727970746f2e736563703235366b312e5075624b657912230a21024f4e2ad99c34d60b9ba6283c9431a8418af8673212961f97a77b6377fcd05b6212040a020801180712140a0e0a05756f736d6f1205353030303010e0a7121a096f736d6f7369732d31202a";
+
+    #[test]
+    fn sign_at_signs_the_expected_doc() {
+        let signer = Signer::new(MNEMONIC).unwrap();
+        let address = signer.get_account_address().to_string();
+        let msg = MsgSend {
+            from_address: address.clone(),
+            to_address: address,
+            amount: vec![ProtoCoin { denom: "uosmo".to_string(), amount: "1000000".to_string() }],
+        };
+        let tx = TxBuilder::new(&signer)
+            .message(Any { type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(), value: msg.encode_to_vec() })
+            .memo("golden")
+            .sign_at(1_000_200, 42, 7)
+            .unwrap();
+
+        let chain_id = Id::try_from(ChainType::Osmosis.chain_id().clone()).unwrap();
+        let sign_doc = SignDoc::new(&tx.body, &tx.auth_info, &chain_id, 42).unwrap();
+        assert_eq!(hex::encode(sign_doc.into_bytes().unwrap()), SIGN_DOC_HEX);
+    }
+}
@@ -1,12 +1,34 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::io::{self, Write};
 use num_format::{Locale, ToFormattedString};
 use log::{info, error};
-use crate::{key_manager::get_account_from_prompt, streamer::Streamer, config::CONFIG};
+use crate::{key_manager::get_account_from_prompt, streamer::{CatchUpPolicy, Streamer}, config::CONFIG};
 use crate::chains::osmosis::osmosis_key_service::Signer;
-use crate::chains::osmosis::osmosis_account_service::fetch_balances;
-use crate::chains::osmosis::osmosis_transaction::summarize_transactions;
-use crate::chains::coin::CoinAmount;
+use crate::chains::osmosis::osmosis_account_service::{fetch_balances, fetch_denom_balance};
+use crate::chains::osmosis::osmosis_transaction::{joined_pools, summarize_transactions};
+use crate::chains::osmosis::osmosis_cl_service::{self, DenomAmount};
+use crate::chains::osmosis::osmosis_lp_service;
+use crate::chains::osmosis::osmosis_pool_service::fetch_gamm_pool;
+use crate::chains::osmosis::osmosis_reconcile_service::reconcile;
+use crate::trade_outcome::NextAction;
+use crate::chains::coin::{parse_coin, Coin, CoinAmount};
+use crate::chains::osmosis::osmosis_transfer_service::{self, parse_timeout_height, IbcRoute, Transfer};
+use crate::trade_service::available_balance;
+use crate::ladder::{Ladder, LadderTier};
+use crate::campaign::{parse_datetime, Campaign};
+use crate::guard_rails;
+use crate::market_maker::{InventoryBounds, MarketMaker};
+use crate::config::PriceReference;
+use crate::rebalance::{compute_drift, RebalanceTarget, Weights};
+use crate::chains::osmosis::osmosis_transaction::executed_amount_since;
+use crate::schedule::{parse_time, Schedule};
+use crate::sweep::SweepPolicy;
+use crate::pnl::{pnl_report, CostMethod};
+use crate::period_summary::{format_table, summarize_by_period, Period};
+use crate::export::{export_rows, write_rows, ExportFilter, ExportFormat};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveTime, Utc};
 
 /// Stream CLI - Automate your crypto trading strategy
 #[derive(Parser, Debug)]
@@ -19,22 +41,45 @@ pub struct TSCli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Start the stream with specified parameters
-    Stream {
-        /// Amount in goal per day
-        #[arg(short = 'i', long, required_unless_present = "daily_amount_in")]
-        daily_amount_out: Option<f64>,
+    Stream(Box<StreamArgs>),
 
-        /// Amount out goal per day
-        #[arg(short = 'o', long, required_unless_present = "daily_amount_out")]
-        daily_amount_in: Option<f64>,
+    /// Buy and sell around a reference price to keep the pool price within a band
+    MarketMake(Box<MarketMakeArgs>),
 
-        /// Streams per day
+    /// Stream swaps until the account holds the target weights by value
+    Rebalance(Box<RebalanceArgs>),
+
+    /// Manage concentrated liquidity positions
+    Cl {
+        #[command(subcommand)]
+        command: ClCommands,
+    },
+
+    /// Join or exit a default (gamm) pool
+    Lp {
+        #[command(subcommand)]
+        command: LpCommands,
+    },
+
+    /// Send tokens to another Osmosis account
+    Send(TransferArgs),
+
+    /// Send tokens to an account on another chain over IBC
+    IbcTransfer {
+        #[command(flatten)]
+        transfer: TransferArgs,
+
+        /// Source channel on Osmosis, e.g. channel-0
         #[arg(long)]
-        daily_streams: u64,
+        channel: String,
 
-        /// Target price
-        #[arg(short, long)]
-        min_price: f64,
+        /// Timeout height on the destination chain, <revision number>-<revision height>
+        #[arg(long, value_parser = parse_timeout_height)]
+        timeout_height: Option<(u64, u64)>,
+
+        /// Seconds before the transfer times out [default: 600 without timeout height]
+        #[arg(long)]
+        timeout_secs: Option<u64>,
     },
 
     /// Query the balances of an account given an address
@@ -45,21 +90,393 @@ pub enum Commands {
     },
 
     /// Summarize all transactions for all accounts
-    Summary,
+    Summary {
+        /// Group the swaps by period, printed as a table
+        #[arg(long, value_enum)]
+        by: Option<Period>,
+
+        /// Only transactions broadcasted at or after this time, YYYY-MM-DD or RFC 3339
+        #[arg(long, value_parser = parse_datetime)]
+        from: Option<DateTime<Utc>>,
+
+        /// Only transactions broadcasted before this time, YYYY-MM-DD or RFC 3339
+        #[arg(long, value_parser = parse_datetime)]
+        to: Option<DateTime<Utc>>,
+
+        /// Print the periods as JSON instead of a table
+        #[arg(long, requires = "by")]
+        json: bool,
+    },
+
+    /// Report cost basis and realized P&L of the swaps of all accounts
+    Pnl {
+        /// How disposals are matched against acquisitions
+        #[arg(long, value_enum, default_value_t = CostMethod::Fifo)]
+        method: CostMethod,
+
+        /// Token the swaps are valued in, defaults to the configured token_out
+        #[arg(long, value_parser = parse_coin)]
+        quote: Option<Coin>,
+    },
+
+    /// Export the transaction history to CSV or JSON Lines
+    Export {
+        /// File format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// File to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Only transactions broadcasted at or after this time, YYYY-MM-DD or RFC 3339
+        #[arg(long, value_parser = parse_datetime)]
+        from: Option<DateTime<Utc>>,
+
+        /// Only transactions broadcasted before this time, YYYY-MM-DD or RFC 3339
+        #[arg(long, value_parser = parse_datetime)]
+        to: Option<DateTime<Utc>>,
+
+        /// Only transactions of this account
+        #[arg(short, long)]
+        address: Option<String>,
+
+        /// Only transactions with this status
+        #[arg(long, value_parser = ["broadcasted", "executed", "error", "timeout"])]
+        status: Option<String>,
+    },
+
+    /// Reconcile the transaction history of an account with the chain
+    Reconcile {
+        /// The account address to reconcile
+        #[arg(short, long)]
+        address: String,
+
+        /// Number of the latest transactions of the account fetched from the chain
+        #[arg(long, default_value_t = 1000)]
+        limit: usize,
+
+        /// Report the discrepancies without changing the transaction history
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Resume streaming after a guard rail halt
+    Resume {
+        /// The account address that was halted
+        #[arg(short, long)]
+        address: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClCommands {
+    /// Open a position over a tick range
+    Create {
+        /// Concentrated liquidity pool, defaults to the configured pool
+        #[arg(long)]
+        pool_id: Option<u64>,
+
+        /// Lower tick of the range, included
+        #[arg(long, allow_negative_numbers = true)]
+        lower_tick: i64,
+
+        /// Upper tick of the range, excluded
+        #[arg(long, allow_negative_numbers = true)]
+        upper_tick: i64,
+
+        /// Amount of the pool token0 to provide
+        #[arg(long, default_value_t = 0.0)]
+        amount0: f64,
+
+        /// Amount of the pool token1 to provide
+        #[arg(long, default_value_t = 0.0)]
+        amount1: f64,
+
+        /// Min amount of token0 actually deposited
+        #[arg(long, default_value_t = 0.0)]
+        min_amount0: f64,
+
+        /// Min amount of token1 actually deposited
+        #[arg(long, default_value_t = 0.0)]
+        min_amount1: f64,
+    },
+
+    /// Add liquidity to a position
+    AddTo {
+        /// The position to add to
+        #[arg(long)]
+        position_id: u64,
+
+        /// Amount of the pool token0 to add
+        #[arg(long, default_value_t = 0.0)]
+        amount0: f64,
+
+        /// Amount of the pool token1 to add
+        #[arg(long, default_value_t = 0.0)]
+        amount1: f64,
+
+        /// Min amount of token0 actually deposited
+        #[arg(long, default_value_t = 0.0)]
+        min_amount0: f64,
+
+        /// Min amount of token1 actually deposited
+        #[arg(long, default_value_t = 0.0)]
+        min_amount1: f64,
+    },
+
+    /// Withdraw liquidity from a position
+    Withdraw {
+        /// The position to withdraw from
+        #[arg(long)]
+        position_id: u64,
+
+        /// Share of the position liquidity to withdraw, in percent
+        #[arg(long, default_value_t = 100.0)]
+        share_pct: f64,
+    },
+
+    /// Collect the spread rewards of positions
+    Collect {
+        /// The positions to collect from, comma separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        position_ids: Vec<u64>,
+
+        /// Also collect the incentives
+        #[arg(long)]
+        incentives: bool,
+    },
+
+    /// List the positions of an account with their value and range status
+    View {
+        /// The account address to query
+        #[arg(short, long)]
+        address: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LpCommands {
+    /// Provide liquidity, with both pool assets or a single one
+    Join {
+        /// Default pool, defaults to the configured pool
+        #[arg(long)]
+        pool_id: Option<u64>,
+
+        /// Max amount of the first pool asset to provide
+        #[arg(long, default_value_t = 0.0)]
+        amount0: f64,
+
+        /// Max amount of the second pool asset to provide
+        #[arg(long, default_value_t = 0.0)]
+        amount1: f64,
+
+        /// Max shortfall from the expected shares, in basis points
+        #[arg(long, default_value_t = 100)]
+        max_slippage_bps: u64,
+    },
+
+    /// Redeem pool shares for the pool assets
+    Exit {
+        /// Default pool, defaults to the configured pool
+        #[arg(long)]
+        pool_id: Option<u64>,
+
+        /// Share of the pool shares held to redeem, in percent
+        #[arg(long, default_value_t = 100.0)]
+        share_pct: f64,
+
+        /// Max shortfall from the expected amounts, in basis points
+        #[arg(long, default_value_t = 100)]
+        max_slippage_bps: u64,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct TransferArgs {
+    /// The destination address
+    #[arg(long)]
+    pub to: String,
+
+    /// The token to send, e.g. USDC
+    #[arg(long, value_parser = parse_coin)]
+    pub token: Coin,
+
+    /// Amount of the token to send
+    #[arg(long)]
+    pub amount: f64,
+
+    /// Memo of the transaction
+    #[arg(long, default_value = "")]
+    pub memo: String,
+}
+
+#[derive(Args, Debug)]
+pub struct StreamArgs {
+    /// Amount in goal per day
+    #[arg(short = 'i', long, required_unless_present_any = ["daily_amount_in", "ladder_in", "ladder_out"])]
+    pub daily_amount_out: Option<f64>,
+
+    /// Amount out goal per day
+    #[arg(short = 'o', long, required_unless_present_any = ["daily_amount_out", "ladder_in", "ladder_out"])]
+    pub daily_amount_in: Option<f64>,
+
+    /// Ladder of daily amounts in per min price, e.g. "200@0.10,300@0.12,500@0.15"
+    #[arg(long, conflicts_with_all = ["daily_amount_out", "daily_amount_in", "min_price", "ladder_out"])]
+    pub ladder_in: Option<Ladder>,
+
+    /// Ladder of daily amounts out per min price, e.g. "200@0.10,300@0.12,500@0.15"
+    #[arg(long, conflicts_with_all = ["daily_amount_out", "daily_amount_in", "min_price"])]
+    pub ladder_out: Option<Ladder>,
+
+    /// Streams per day
+    #[arg(long)]
+    pub daily_streams: u64,
+
+    /// Target price
+    #[arg(short, long, required_unless_present_any = ["ladder_in", "ladder_out"])]
+    pub min_price: Option<f64>,
+
+    /// Max slippage from the price quoted right before signing, in basis points
+    #[arg(long)]
+    pub max_slippage_bps: Option<u64>,
+
+    /// How volume missed in a window is made up for [default: none, next-window in ladder mode]
+    #[arg(long, value_enum)]
+    pub catch_up: Option<CatchUpPolicy>,
+
+    /// Max amount of a single trade, caps catch-up trades
+    #[arg(long)]
+    pub max_trade_amount: Option<f64>,
+
+    /// Randomize each window amount by up to this percent, the day's total is kept
+    #[arg(long, default_value_t = 0.0)]
+    pub size_jitter_pct: f64,
+
+    /// Total amount of the campaign, the daily amount becomes a max daily rate
+    #[arg(long, requires = "start_at", conflicts_with_all = ["ladder_in", "ladder_out"])]
+    pub total_amount: Option<f64>,
+
+    /// Campaign start, YYYY-MM-DD or RFC 3339, trades since then count towards the total amount
+    #[arg(long, requires = "total_amount", value_parser = parse_datetime)]
+    pub start_at: Option<DateTime<Utc>>,
+
+    /// Campaign end, YYYY-MM-DD or RFC 3339
+    #[arg(long, requires = "total_amount", value_parser = parse_datetime)]
+    pub end_at: Option<DateTime<Utc>>,
+
+    /// Trading hours as "[days] [hours] [time zone]", e.g. "Mon-Fri 08:00-18:00 Europe/Paris"
+    #[arg(long)]
+    pub schedule: Option<Schedule>,
+
+    /// UTC time at which the day and its windows start, HH:MM
+    #[arg(long, default_value = "00:00", value_parser = parse_time)]
+    pub daily_reset_utc: NaiveTime,
+}
+
+#[derive(Args, Debug)]
+pub struct MarketMakeArgs {
+    /// Half width of the band around the reference price, in basis points
+    #[arg(long)]
+    pub spread_bps: u64,
+
+    /// Fixed reference price, defaults to the configured TWAP or oracle reference
+    #[arg(long)]
+    pub reference_price: Option<f64>,
+
+    /// Amount of token in bought or sold per trade
+    #[arg(long)]
+    pub trade_amount: f64,
+
+    /// Max amount of token in bought and sold per day
+    #[arg(long)]
+    pub daily_volume_cap: Option<f64>,
+
+    /// Min balance of token in to keep
+    #[arg(long)]
+    pub min_inventory_in: Option<f64>,
+
+    /// Max balance of token in to hold
+    #[arg(long)]
+    pub max_inventory_in: Option<f64>,
+
+    /// Min balance of token out to keep
+    #[arg(long)]
+    pub min_inventory_out: Option<f64>,
+
+    /// Max balance of token out to hold
+    #[arg(long)]
+    pub max_inventory_out: Option<f64>,
+
+    /// Seconds between two price checks
+    #[arg(long, default_value_t = 60)]
+    pub interval_secs: u64,
+
+    /// Max slippage from the price quoted right before signing, in basis points
+    #[arg(long)]
+    pub max_slippage_bps: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct RebalanceArgs {
+    /// Target weights by value, e.g. "USDC=60,WLibra=40"
+    #[arg(long)]
+    pub weights: Weights,
+
+    /// Accepted distance from the target weights, in percentage points
+    #[arg(long, default_value_t = 1.0)]
+    pub tolerance_pct: f64,
+
+    /// Days over which the drift is traded
+    #[arg(long, default_value_t = 1)]
+    pub days: u64,
+
+    /// Streams per day
+    #[arg(long)]
+    pub daily_streams: u64,
+
+    /// Min price of the token sold, none by default
+    #[arg(short, long)]
+    pub min_price: Option<f64>,
+
+    /// Max slippage from the price quoted right before signing, in basis points
+    #[arg(long, default_value_t = 100)]
+    pub max_slippage_bps: u64,
 }
 
 impl TSCli {
     pub async fn run(&self) {
         match &self.command {
-            Commands::Stream {
-                daily_amount_out,
-                daily_amount_in,
-                daily_streams,
-                min_price,
-            } => {
+            Commands::Stream(args) => {
                 // Existing logic for starting the stream
-                self.run_stream(*daily_amount_out, *daily_amount_in, *daily_streams, *min_price)
-                    .await;
+                self.run_stream(args).await;
+            }
+
+            Commands::MarketMake(args) => {
+                self.run_market_make(args).await;
+            }
+
+            Commands::Rebalance(args) => {
+                self.run_rebalance(args).await;
+            }
+
+            Commands::Cl { command } => {
+                self.run_cl(command).await;
+            }
+
+            Commands::Lp { command } => {
+                self.run_lp(command).await;
+            }
+
+            Commands::Send(args) => {
+                self.run_transfer(args, None).await;
+            }
+
+            Commands::IbcTransfer { transfer, channel, timeout_height, timeout_secs } => {
+                // Without any timeout, the transfer expires after 10 minutes
+                let timeout_secs = timeout_secs.or(if timeout_height.is_none() { Some(600) } else { None });
+                let route = IbcRoute { channel: channel.clone(), timeout_height: *timeout_height, timeout_secs };
+                self.run_transfer(transfer, Some(route)).await;
             }
 
             Commands::Balance { address } => {
@@ -67,110 +484,533 @@ impl TSCli {
                 self.run_balance(address).await;
             }
 
-            Commands::Summary => {
-                self.run_summary().await;
+            Commands::Summary { by, from, to, json } => {
+                let from = from.map(|from| from.timestamp() as u64);
+                let to = to.map(|to| to.timestamp() as u64);
+                match by {
+                    Some(period) => self.run_period_summary(*period, from, to, *json),
+                    None => self.run_summary(from, to).await,
+                }
+            }
+            Commands::Pnl { method, quote } => {
+                self.run_pnl(*method, quote.unwrap_or(CONFIG.env_constants.token_out));
+            }
+            Commands::Export { format, output, from, to, address, status } => {
+                let filter = ExportFilter { from: *from, to: *to, account: address.clone(), status: status.clone() };
+                self.run_export(&filter, *format, output);
+            }
+
+            Commands::Reconcile { address, limit, dry_run } => {
+                self.run_reconcile(address, *limit, *dry_run).await;
+            }
+            Commands::Resume { address } => {
+                self.run_resume(address);
             }
         }
     }
 
     // Method to handle the 'stream' subcommand
-    async fn run_stream(
-        &self,
-        daily_amount_out: Option<f64>,
-        daily_amount_in: Option<f64>,
-        daily_streams: u64,
-        min_price: f64,
-    ) {
+    async fn run_stream(&self, args: &StreamArgs) {
+        let daily_streams = args.daily_streams;
+        let max_slippage_bps = args.max_slippage_bps;
+
         // Check if the user has provided valid parameters
-        if daily_streams <= 0 || min_price <= 0.0 {
+        if daily_streams == 0 || args.min_price.is_some_and(|price| price <= 0.0) {
             error!("Invalid parameters provided. Please provide valid values for daily_amount_out, daily_streams, and min_price");
             std::process::exit(0);
         }
+        if max_slippage_bps.is_some_and(|bps| bps >= 10_000) {
+            error!("Invalid max_slippage_bps provided. Please provide a value below 10000");
+            std::process::exit(0);
+        }
+        if !(0.0..100.0).contains(&args.size_jitter_pct) {
+            error!("Invalid size_jitter_pct provided. Please provide a value from 0 to below 100");
+            std::process::exit(0);
+        }
+        let max_trade_amount = args.max_trade_amount.map(|amount| (amount * 1_000_000.0) as u64);
+        if max_trade_amount == Some(0) {
+            error!("Invalid max_trade_amount provided. Please provide a positive value");
+            std::process::exit(0);
+        }
 
-        // Get the daily amount out or in based on the user input
-        let (swap_type, amount) = if let Some(amount_out) = daily_amount_out {
-            ("amount_out", (amount_out * 1_000_000.0) as u64)
-        } else if let Some(amount_in) = daily_amount_in {
-            ("amount_in", (amount_in * 1_000_000.0) as u64)
+        // Get the daily amount out or in based on the user input, a single amount is a one tier ladder
+        let (swap_type, tiers, ladder) = if let Some(Ladder(tiers)) = &args.ladder_in {
+            ("amount_in", tiers.clone(), true)
+        } else if let Some(Ladder(tiers)) = &args.ladder_out {
+            ("amount_out", tiers.clone(), true)
         } else {
-            unreachable!()
+            let min_price = args.min_price.unwrap();
+            let (swap_type, amount) = if let Some(amount_out) = args.daily_amount_out {
+                ("amount_out", (amount_out * 1_000_000.0) as u64)
+            } else if let Some(amount_in) = args.daily_amount_in {
+                ("amount_in", (amount_in * 1_000_000.0) as u64)
+            } else {
+                unreachable!()
+            };
+
+            // Check if the user has provided a valid amount
+            if amount == 0 {
+                error!("Invalid amount provided. Please provide a valid value for daily_amount_out or daily_amount_in");
+                std::process::exit(0);
+            }
+            (swap_type, vec![LadderTier { daily_amount: amount, min_price }], false)
         };
 
-        // Check if the user has provided a valid amount
-        if amount <= 0 {
-            error!("Invalid amount provided. Please provide a valid value for daily_amount_out or daily_amount_in");
+        let total_amount = args.total_amount.map(|amount| (amount * 1_000_000.0) as u64);
+        if total_amount == Some(0) || args.end_at.is_some_and(|end_at| Some(end_at) <= args.start_at) {
+            error!("Invalid campaign provided. Please provide a positive total_amount and an end_at after start_at");
             std::process::exit(0);
         }
 
-        // Get mnemonic from user
-        let mnemonic = match get_account_from_prompt("Osmosis") {
-            Ok(ret) => ret,
-            Err(e) => {
-                error!("Error getting account keys: {:?}", e);
-                std::process::exit(0);
+        // Get the signer, the account must not be halted
+        let signer = signer_from_prompt();
+        exit_if_halted(signer.get_account_address());
+
+        // Fetch balances
+        let balances = fetch_balances_or_exit(signer.get_account_address()).await;
+
+        // Read the campaign progress from the ledger
+        let campaign = match (total_amount, args.start_at) {
+            (Some(total_amount), Some(start_at)) => {
+                let filled = match executed_amount_since(
+                    signer.get_account_address(),
+                    CONFIG.env_constants.pool_id,
+                    CONFIG.env_constants.token_in,
+                    CONFIG.env_constants.token_out,
+                    swap_type,
+                    start_at.timestamp().max(0) as u64,
+                ) {
+                    Ok(filled) => filled,
+                    Err(e) => {
+                        error!("Error reading campaign progress: {:?}", e);
+                        std::process::exit(0);
+                    }
+                };
+                let campaign = Campaign { total_amount, start_at, end_at: args.end_at, filled };
+                if let Some(reason) = campaign.finish_reason(Utc::now()) {
+                    info!("Campaign is over: {} ({})", reason, campaign);
+                    std::process::exit(0);
+                }
+                Some(campaign)
             }
+            _ => None,
         };
 
-        // Create signer
-        let signer = match Signer::new(&mnemonic) {
-            Ok(ret) => ret,
+        let mut streamer = Streamer::new(tiers, ladder, swap_type, daily_streams, max_slippage_bps);
+        let catch_up = args.catch_up.unwrap_or(streamer.catch_up);
+        streamer = streamer
+            .with_catch_up(catch_up, max_trade_amount)
+            .with_size_jitter(args.size_jitter_pct)
+            .with_schedule(args.schedule.clone(), args.daily_reset_utc);
+        if let Some(campaign) = campaign {
+            streamer = streamer.with_campaign(campaign);
+        }
+        if let Some(sweep) = sweep_policy_or_exit() {
+            streamer = streamer.with_sweep(sweep);
+        }
+
+        // Confirm address and parameters
+        if get_user_confirmation(signer.get_account_address(), balances, &streamer) {
+            println!("Proceeding...\n");
+        } else {
+            println!("Exiting...\n");
+            std::process::exit(0);
+        }
+
+        streamer.start(&signer).await;
+
+        info!("Stream service stopped.");
+    }
+
+    // Method to handle the 'market-make' subcommand
+    async fn run_market_make(&self, args: &MarketMakeArgs) {
+        let to_microns = |amount: Option<f64>| amount.map(|amount| (amount * 1_000_000.0) as u64);
+
+        // Check if the user has provided valid parameters
+        if args.spread_bps == 0 || args.spread_bps >= 10_000 || args.trade_amount <= 0.0 || args.interval_secs == 0 || args.reference_price.is_some_and(|price| price <= 0.0) {
+            error!("Invalid parameters provided. Please provide valid values for spread_bps, trade_amount, interval_secs and reference_price");
+            std::process::exit(0);
+        }
+        if args.max_slippage_bps.is_some_and(|bps| bps >= 10_000) {
+            error!("Invalid max_slippage_bps provided. Please provide a value below 10000");
+            std::process::exit(0);
+        }
+        if args.reference_price.is_none() && CONFIG.price_validation.reference == PriceReference::None {
+            error!("No reference price. Please provide reference_price or configure a price_validation reference");
+            std::process::exit(0);
+        }
+
+        let market_maker = MarketMaker {
+            reference_price: args.reference_price,
+            spread_bps: args.spread_bps,
+            trade_amount: (args.trade_amount * 1_000_000.0) as u64,
+            daily_volume_cap: to_microns(args.daily_volume_cap),
+            inventory_in: InventoryBounds { min: to_microns(args.min_inventory_in), max: to_microns(args.max_inventory_in) },
+            inventory_out: InventoryBounds { min: to_microns(args.min_inventory_out), max: to_microns(args.max_inventory_out) },
+            interval_secs: args.interval_secs,
+            max_slippage_bps: args.max_slippage_bps,
+        };
+
+        // Get the signer, the account must not be halted
+        let signer = signer_from_prompt();
+        exit_if_halted(signer.get_account_address());
+
+        // Fetch balances
+        let balances = fetch_balances_or_exit(signer.get_account_address()).await;
+
+        // Confirm address and parameters
+        if get_market_maker_confirmation(signer.get_account_address(), balances, &market_maker) {
+            println!("Proceeding...\n");
+        } else {
+            println!("Exiting...\n");
+            std::process::exit(0);
+        }
+
+        market_maker.start(&signer).await;
+
+        info!("Market making service stopped.");
+    }
+
+    // Method to handle the 'rebalance' subcommand
+    async fn run_rebalance(&self, args: &RebalanceArgs) {
+        // Check if the user has provided valid parameters
+        if args.days == 0 || args.daily_streams == 0 || args.tolerance_pct <= 0.0 || args.max_slippage_bps >= 10_000 || args.min_price.is_some_and(|price| price <= 0.0) {
+            error!("Invalid parameters provided. Please provide valid values for days, daily_streams, tolerance_pct, max_slippage_bps and min_price");
+            std::process::exit(0);
+        }
+        let target = match RebalanceTarget::from_weights(&args.weights, args.tolerance_pct) {
+            Ok(target) => target,
             Err(e) => {
-                error!("Error creating signer: {:?}", e);
+                error!("Invalid weights provided: {}", e);
                 std::process::exit(0);
             }
         };
 
+        // Get the signer, the account must not be halted
+        let signer = signer_from_prompt();
+        exit_if_halted(signer.get_account_address());
+
         // Fetch balances
-        let balances = match fetch_balances(signer.get_account_address(), None).await {
-            Ok(balances) => balances,
+        let balances = fetch_balances_or_exit(signer.get_account_address()).await;
+
+        // Compute how far the portfolio is from the target
+        let drift = match compute_drift(signer.get_account_address(), &target).await {
+            Ok(Some(drift)) => drift,
+            Ok(None) => {
+                println!("Portfolio is already within {}% of its target", args.tolerance_pct);
+                std::process::exit(0);
+            }
             Err(e) => {
-                error!("Error fetching account balances: {:?}", e);
+                error!("Error computing the portfolio drift: {:?}", e);
                 std::process::exit(0);
             }
         };
+        let (token_in, token_out) = if drift.sell == CONFIG.env_constants.token_in {
+            (CONFIG.env_constants.token_in, CONFIG.env_constants.token_out)
+        } else {
+            (CONFIG.env_constants.token_out, CONFIG.env_constants.token_in)
+        };
+
+        // The drift is sold over the given days
+        let daily_amount = drift.amount.div_ceil(args.days);
+        let tiers = vec![LadderTier { daily_amount, min_price: args.min_price.unwrap_or(0.0) }];
+        let streamer = Streamer::new(tiers, false, "amount_in", args.daily_streams, Some(args.max_slippage_bps))
+            .with_rebalance(target, token_in, token_out);
+        if sweep_policy_or_exit().is_some() {
+            info!("Sweeping is not applied to rebalancing, the proceeds are part of the target portfolio");
+        }
 
         // Confirm address and parameters
-        if get_user_confirmation(
-            &signer.get_account_address(),
-            balances,
-            amount,
-            swap_type,
-            daily_streams,
-            min_price,
-        ) {
+        println!("\nTarget: {:.2}% in {}, current: {}", target.weight_in * 100.0, CONFIG.env_constants.token_in, drift);
+        if get_user_confirmation(signer.get_account_address(), balances, &streamer) {
             println!("Proceeding...\n");
         } else {
             println!("Exiting...\n");
             std::process::exit(0);
         }
 
-        let streamer = Streamer::new(amount, swap_type, daily_streams, min_price);
         streamer.start(&signer).await;
 
-        info!("Stream service stopped.");
+        info!("Rebalance service stopped.");
     }
 
-    // Method to handle the 'balance' subcommand
-    async fn run_balance(&self, address: &String) {
-        // Fetch balances
-        let balances = match fetch_balances(&address, None).await {
-            Ok(balances) => balances,
+    // Method to handle the 'cl' subcommands
+    async fn run_cl(&self, command: &ClCommands) {
+        let to_microns = |amount: f64| (amount * 1_000_000.0) as u64;
+
+        if let ClCommands::View { address } = command {
+            if !print_cl_positions(address).await {
+                println!("\nNo concentrated liquidity positions for account: {}", address);
+            }
+            return;
+        }
+
+        // Check if the user has provided valid parameters
+        match command {
+            ClCommands::Create { lower_tick, upper_tick, amount0, amount1, .. } if lower_tick >= upper_tick || (*amount0 <= 0.0 && *amount1 <= 0.0) => {
+                error!("Invalid parameters provided. Please provide a lower_tick below upper_tick and a positive amount0 or amount1");
+                std::process::exit(0);
+            }
+            ClCommands::AddTo { amount0, amount1, .. } if *amount0 <= 0.0 && *amount1 <= 0.0 => {
+                error!("Invalid parameters provided. Please provide a positive amount0 or amount1");
+                std::process::exit(0);
+            }
+            ClCommands::Withdraw { share_pct, .. } if *share_pct <= 0.0 || *share_pct > 100.0 => {
+                error!("Invalid share_pct provided. Please provide a value above 0 and up to 100");
+                std::process::exit(0);
+            }
+            _ => {}
+        }
+
+        // Get the signer, the account must not be halted
+        let signer = signer_from_prompt();
+        let address = signer.get_account_address();
+        exit_if_halted(address);
+
+        // Amounts of the pool tokens deposited, in microns
+        let (pool_id, deposits) = match command {
+            ClCommands::Create { pool_id, amount0, amount1, .. } => {
+                let pool_id = pool_id.unwrap_or(CONFIG.env_constants.pool_id);
+                let pool = match osmosis_cl_service::fetch_cl_pool(pool_id).await {
+                    Ok(pool) => pool,
+                    Err(e) => {
+                        error!("Error fetching pool {}: {:?}", pool_id, e);
+                        std::process::exit(0);
+                    }
+                };
+                let deposits = vec![
+                    DenomAmount { denom: pool.token0, amount: to_microns(*amount0).to_string() },
+                    DenomAmount { denom: pool.token1, amount: to_microns(*amount1).to_string() },
+                ];
+                (Some(pool_id), deposits)
+            }
+            ClCommands::AddTo { position_id, amount0, amount1, .. } => {
+                let position = match osmosis_cl_service::fetch_position(address, *position_id).await {
+                    Ok(position) => position,
+                    Err(e) => {
+                        error!("Error fetching position {}: {:?}", position_id, e);
+                        std::process::exit(0);
+                    }
+                };
+                let deposits = vec![
+                    DenomAmount { denom: position.asset0.denom, amount: to_microns(*amount0).to_string() },
+                    DenomAmount { denom: position.asset1.denom, amount: to_microns(*amount1).to_string() },
+                ];
+                (Some(position.pool_id), deposits)
+            }
+            _ => (None, Vec::new()),
+        };
+
+        // Check the balances and confirm the transaction
+        exit_if_insufficient(address, &deposits).await;
+        let balances = fetch_balances_or_exit(address).await;
+        if get_cl_confirmation(address, balances, command, pool_id, &deposits) {
+            println!("Proceeding...\n");
+        } else {
+            println!("Exiting...\n");
+            std::process::exit(0);
+        }
+
+        let result = match command {
+            ClCommands::Create { pool_id, lower_tick, upper_tick, amount0, amount1, min_amount0, min_amount1 } => {
+                let pool_id = pool_id.unwrap_or(CONFIG.env_constants.pool_id);
+                osmosis_cl_service::create_position(&signer, pool_id, *lower_tick, *upper_tick, (to_microns(*amount0), to_microns(*amount1)), (to_microns(*min_amount0), to_microns(*min_amount1))).await
+            }
+            ClCommands::AddTo { position_id, amount0, amount1, min_amount0, min_amount1 } => {
+                osmosis_cl_service::add_to_position(&signer, *position_id, (to_microns(*amount0), to_microns(*amount1)), (to_microns(*min_amount0), to_microns(*min_amount1))).await
+            }
+            ClCommands::Withdraw { position_id, share_pct } => {
+                osmosis_cl_service::withdraw_position(&signer, *position_id, *share_pct).await
+            }
+            ClCommands::Collect { position_ids, incentives } => {
+                osmosis_cl_service::collect(&signer, position_ids.clone(), *incentives).await
+            }
+            ClCommands::View { .. } => unreachable!(),
+        };
+
+        match result {
+            Ok(outcome) if outcome.next_action() == NextAction::Done => {
+                println!("Transaction executed with success\n");
+                print_cl_positions(signer.get_account_address()).await;
+            }
+            Ok(outcome) => error!("Transaction not executed: {}", outcome),
+            Err(e) => error!("Error sending the transaction: {:?}", e),
+        }
+    }
+
+    // Method to handle the 'lp' subcommands
+    async fn run_lp(&self, command: &LpCommands) {
+        let to_microns = |amount: f64| (amount * 1_000_000.0) as u64;
+
+        // Check if the user has provided valid parameters
+        match command {
+            LpCommands::Join { amount0, amount1, max_slippage_bps, .. } if (*amount0 <= 0.0 && *amount1 <= 0.0) || *max_slippage_bps >= 10_000 => {
+                error!("Invalid parameters provided. Please provide a positive amount0 or amount1 and a max_slippage_bps below 10000");
+                std::process::exit(0);
+            }
+            LpCommands::Exit { share_pct, max_slippage_bps, .. } if *share_pct <= 0.0 || *share_pct > 100.0 || *max_slippage_bps >= 10_000 => {
+                error!("Invalid parameters provided. Please provide a share_pct above 0 and up to 100 and a max_slippage_bps below 10000");
+                std::process::exit(0);
+            }
+            _ => {}
+        }
+
+        // Get the signer, the account must not be halted
+        let signer = signer_from_prompt();
+        let address = signer.get_account_address();
+        exit_if_halted(address);
+
+        let pool_id = match command {
+            LpCommands::Join { pool_id, .. } | LpCommands::Exit { pool_id, .. } => pool_id.unwrap_or(CONFIG.env_constants.pool_id),
+        };
+        let pool = match fetch_gamm_pool(pool_id).await {
+            Ok(pool) => pool,
             Err(e) => {
-                error!("Error fetching account balances: {:?}", e);
+                error!("Error fetching pool {}: {:?}", pool_id, e);
                 std::process::exit(0);
             }
         };
 
+        // Amounts of the pool assets deposited, in microns. Only two asset pools can be joined.
+        let deposits = match command {
+            LpCommands::Join { amount0, amount1, .. } => {
+                let denoms = pool.denoms();
+                if denoms.len() != 2 {
+                    error!("Pool {} has {} assets, only pools of two assets can be joined", pool_id, denoms.len());
+                    std::process::exit(0);
+                }
+                denoms
+                    .into_iter()
+                    .zip([*amount0, *amount1])
+                    .map(|(denom, amount)| DenomAmount { denom: denom.to_string(), amount: to_microns(amount).to_string() })
+                    .collect()
+            }
+            LpCommands::Exit { .. } => Vec::new(),
+        };
+
+        // Check the balances and confirm the transaction
+        exit_if_insufficient(address, &deposits).await;
+        let balances = fetch_balances_or_exit(address).await;
+        if get_lp_confirmation(address, balances, command, pool_id, pool.share_denom(), &deposits) {
+            println!("Proceeding...\n");
+        } else {
+            println!("Exiting...\n");
+            std::process::exit(0);
+        }
+
+        let amounts: Vec<u64> = deposits.iter().map(|deposit| deposit.amount.parse().unwrap_or(0)).collect();
+        let result = match command {
+            LpCommands::Join { max_slippage_bps, .. } => osmosis_lp_service::join_pool(&signer, pool_id, &amounts, *max_slippage_bps).await,
+            LpCommands::Exit { share_pct, max_slippage_bps, .. } => osmosis_lp_service::exit_pool(&signer, pool_id, *share_pct, *max_slippage_bps).await,
+        };
+
+        match result {
+            Ok(outcome) if outcome.next_action() == NextAction::Done => println!("Transaction executed with success\n"),
+            Ok(outcome) => error!("Transaction not executed: {}", outcome),
+            Err(e) => error!("Error sending the transaction: {:?}", e),
+        }
+    }
+
+    // Method to handle the 'send' and 'ibc-transfer' subcommands
+    async fn run_transfer(&self, args: &TransferArgs, ibc: Option<IbcRoute>) {
+        let amount = (args.amount * 1_000_000.0) as u64;
+        let transfer = Transfer {
+            to_address: args.to.clone(),
+            token: CoinAmount { coin: args.token, amount },
+            memo: args.memo.clone(),
+            ibc,
+        };
+
+        // Check if the user has provided valid parameters
+        if amount == 0 {
+            error!("Invalid amount provided. Please provide a positive value");
+            std::process::exit(0);
+        }
+        if let Err(e) = transfer.validate() {
+            error!("Invalid transfer: {}", e);
+            std::process::exit(0);
+        }
+
+        // Get the signer and check the balance
+        let signer = signer_from_prompt();
+        let balances = fetch_balances_or_exit(signer.get_account_address()).await;
+        if available_balance(&balances, transfer.token.coin) < amount {
+            error!("Insufficient {} balance to send {}", transfer.token.coin, transfer.token);
+            std::process::exit(0);
+        }
+
+        // Confirm address and parameters
+        if get_transfer_confirmation(signer.get_account_address(), balances, &transfer) {
+            println!("Proceeding...\n");
+        } else {
+            println!("Exiting...\n");
+            std::process::exit(0);
+        }
+
+        match osmosis_transfer_service::transfer(&signer, &transfer).await {
+            Ok(outcome) if outcome.next_action() == NextAction::Done => println!("Transfer executed with success\n"),
+            Ok(outcome) => error!("Transfer not executed: {}", outcome),
+            Err(e) => error!("Error sending the transfer: {:?}", e),
+        }
+    }
+
+    // Method to handle the 'balance' subcommand
+    async fn run_balance(&self, address: &String) {
+        // Fetch balances
+        let balances = fetch_balances_or_exit(address).await;
+
         // Display balances
         println!("\nBalances for account: {}", address);
         for balance in balances {
             println!("  {}", balance);
         }
+
+        // Display concentrated liquidity positions
+        print_cl_positions(address).await;
+
+        // Display the shares of the pools joined with `lp join`
+        print_lp_positions(address).await;
+    }
+
+    // Method to handle the 'reconcile' subcommand
+    async fn run_reconcile(&self, address: &str, limit: usize, dry_run: bool) {
+        let report = match reconcile(address, limit, dry_run).await {
+            Ok(report) => report,
+            Err(e) => {
+                error!("Error reconciling transactions: {:?}", e);
+                return;
+            }
+        };
+
+        let verb = if dry_run { "to update" } else { "updated" };
+        println!("\nReconciliation of account: {}", address);
+        println!("  Stale records {}: {}", verb, report.updated.len());
+        for txhash in &report.updated {
+            println!("    - {}", txhash);
+        }
+        let verb = if dry_run { "to import" } else { "imported" };
+        println!("  Swaps sent outside the tool {}: {}", verb, report.imported.len());
+        for txhash in &report.imported {
+            println!("    - {}", txhash);
+        }
+        println!("  Discrepancies: {}", report.discrepancies.len());
+        for discrepancy in &report.discrepancies {
+            println!("    - {}", discrepancy);
+        }
+    }
+
+    // Method to handle the 'resume' subcommand
+    fn run_resume(&self, address: &str) {
+        match guard_rails::resume(address) {
+            Ok(0) => println!("Account {} is not halted", address),
+            Ok(_) => println!("Account {} resumed, the stream can be restarted", address),
+            Err(e) => error!("Error resuming account: {:?}", e),
+        }
     }
 
     // Method to handle the 'summary' subcommand
-    async fn run_summary(&self) {
-        match summarize_transactions() {
+    async fn run_summary(&self, from: Option<u64>, to: Option<u64>) {
+        match summarize_transactions(from, to) {
             Ok(summary) => {
                 println!("Transaction Summary:\n{}", serde_json::to_string_pretty(&summary).unwrap());
             }
@@ -179,10 +1019,59 @@ impl TSCli {
             }
         }
     }
+
+    // Method to handle the 'summary' subcommand grouped by period
+    fn run_period_summary(&self, period: Period, from: Option<u64>, to: Option<u64>, json: bool) {
+        match summarize_by_period(period, from, to) {
+            Ok(summaries) if json => {
+                println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+            }
+            Ok(summaries) if summaries.is_empty() => {
+                println!("No swaps in the period");
+            }
+            Ok(summaries) => {
+                print!("{}", format_table(&summaries));
+            }
+            Err(e) => {
+                error!("Error summarizing transactions: {:?}", e);
+            }
+        }
+    }
+
+    // Method to handle the 'export' subcommand
+    fn run_export(&self, filter: &ExportFilter, format: ExportFormat, output: &Path) {
+        let rows = match export_rows(filter) {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("Error reading transactions: {:?}", e);
+                return;
+            }
+        };
+
+        let result = fs::File::create(output)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", output.display(), e))
+            .and_then(|file| write_rows(&rows, format, io::BufWriter::new(file)));
+        match result {
+            Ok(()) => println!("{} transactions exported to {}", rows.len(), output.display()),
+            Err(e) => error!("Error exporting transactions: {:?}", e),
+        }
+    }
+
+    // Method to handle the 'pnl' subcommand
+    fn run_pnl(&self, method: CostMethod, quote: Coin) {
+        match pnl_report(quote, method) {
+            Ok(report) => {
+                println!("Realized P&L:\n{}", serde_json::to_string_pretty(&report).unwrap());
+            }
+            Err(e) => {
+                error!("Error computing P&L: {:?}", e);
+            }
+        }
+    }
 }
 
 // Function to get user confirmation (y/n)
-fn get_user_confirmation(address: &str, balances: Vec<CoinAmount>, amount: u64, swap_type: &str, daily_streams: u64, min_price: f64) -> bool {   
+fn get_user_confirmation(address: &str, balances: Vec<CoinAmount>, streamer: &Streamer) -> bool {   
     // Ask user to confirm the address and params
     println!("\nPlease confirm the following details for the Trade Stream:");
     println!(" 1. Account Address: {}", address);
@@ -194,34 +1083,199 @@ fn get_user_confirmation(address: &str, balances: Vec<CoinAmount>, amount: u64,
     }
 
     // Display daily amount based on swap type
-    match swap_type {
-        "amount_out" => {
-            let coin_amount = CoinAmount {
-                coin: CONFIG.env_constants.token_out,
-                amount: amount,
-            };
-            println!("\n 2. Daily Amount Out: {}", coin_amount);
-        },
-        "amount_in" => {
-            let coin_amount = CoinAmount {
-                coin: CONFIG.env_constants.token_out,
-                amount: amount,
-            };
-            println!("\n 2. Daily Amount In:  {}", coin_amount);
-        },
+    let (label, coin) = match streamer.swap_type {
+        "amount_out" => ("Daily Amount Out:", streamer.token_out),
+        "amount_in" => ("Daily Amount In: ", streamer.token_in),
         _ => {
-            error!("Invalid swap type: {}", swap_type);
+            error!("Invalid swap type: {}", streamer.swap_type);
             return false;
         }
-    }
+    };
+    let total = CoinAmount {
+        coin,
+        amount: streamer.tiers.iter().map(|tier| tier.daily_amount).sum(),
+    };
+    println!("\n 2. {} {}", label, total);
 
     // Print additional details
-    println!(" 3. Daily Streams:    {}", daily_streams.to_formatted_string(&Locale::en));
-    println!(" 4. Min Price:        {} {}", CONFIG.env_constants.token_out, min_price);
-    println!(" 5. Token In:         {}", CONFIG.env_constants.token_in);
-    println!(" 6. Token Out:        {}", CONFIG.env_constants.token_out);
-    println!(" 7. Pool ID:          {}\n", CONFIG.env_constants.pool_id);
+    println!(" 3. Daily Streams:    {}", streamer.daily_streams.to_formatted_string(&Locale::en));
+    match streamer.tiers.as_slice() {
+        [tier] => println!(" 4. Min Price:        {} {}", streamer.token_out, tier.min_price),
+        tiers => {
+            println!(" 4. Ladder Tiers:");
+            for tier in tiers {
+                println!("    - {} {}", streamer.token_out, tier);
+            }
+        }
+    }
+    match streamer.max_slippage_bps {
+        Some(bps) => println!(" 5. Max Slippage:     {} bps", bps),
+        None => println!(" 5. Max Slippage:     none (bounded by min price only)"),
+    }
+    println!(" 6. Token In:         {}", streamer.token_in);
+    println!(" 7. Token Out:        {}", streamer.token_out);
+    println!(" 8. Pool ID:          {}", CONFIG.env_constants.pool_id);
+    match &streamer.campaign {
+        Some(campaign) => println!(" 9. Campaign:         {}", campaign),
+        None => println!(" 9. Campaign:         none (runs until stopped)"),
+    }
+    match &streamer.schedule {
+        Some(schedule) => println!("10. Schedule:         {}, day starts at {} UTC", schedule, streamer.daily_reset.format("%H:%M")),
+        None => println!("10. Schedule:         always open, day starts at {} UTC", streamer.daily_reset.format("%H:%M")),
+    }
+    match &streamer.sweep {
+        Some(sweep) => println!("11. Sweep:            {}\n", sweep),
+        None => println!("11. Sweep:            none\n"),
+    }
     
+    read_confirmation()
+}
+
+// Function to get user confirmation of the market making parameters (y/n)
+fn get_market_maker_confirmation(address: &str, balances: Vec<CoinAmount>, market_maker: &MarketMaker) -> bool {
+    let token_in = CONFIG.env_constants.token_in;
+    let token_out = CONFIG.env_constants.token_out;
+    let bounds = |bounds: InventoryBounds, coin| {
+        let format = |amount: Option<u64>| amount.map_or("-".to_string(), |amount| CoinAmount { coin, amount }.to_string());
+        format!("min {}, max {}", format(bounds.min), format(bounds.max))
+    };
+
+    println!("\nPlease confirm the following details for the Market Maker:");
+    println!(" 1. Account Address: {}", address);
+
+    // Display account balances
+    println!("\n    Account Balances:");
+    for balance in &balances {
+        println!("    - {}", balance);
+    }
+
+    match market_maker.reference_price {
+        Some(price) => println!("\n 2. Reference Price:  {} {}", token_out, price),
+        None => println!("\n 2. Reference Price:  {:?} reference", CONFIG.price_validation.reference),
+    }
+    println!(" 3. Spread:           {} bps", market_maker.spread_bps);
+    println!(" 4. Trade Amount:     {}", CoinAmount { coin: token_in, amount: market_maker.trade_amount });
+    match market_maker.daily_volume_cap {
+        Some(amount) => println!(" 5. Daily Volume Cap: {}", CoinAmount { coin: token_in, amount }),
+        None => println!(" 5. Daily Volume Cap: none"),
+    }
+    println!(" 6. {} Inventory: {}", token_in, bounds(market_maker.inventory_in, token_in));
+    println!(" 7. {} Inventory: {}", token_out, bounds(market_maker.inventory_out, token_out));
+    println!(" 8. Check Interval:   {} seconds", market_maker.interval_secs);
+    println!(" 9. Pool ID:          {}\n", CONFIG.env_constants.pool_id);
+
+    read_confirmation()
+}
+
+// Function to get user confirmation of a transfer (y/n)
+fn get_transfer_confirmation(address: &str, balances: Vec<CoinAmount>, transfer: &Transfer) -> bool {
+    println!("\nPlease confirm the following details for the Transfer:");
+    println!(" 1. Account Address: {}", address);
+
+    // Display account balances
+    println!("\n    Account Balances:");
+    for balance in &balances {
+        println!("    - {}", balance);
+    }
+
+    println!("\n 2. Destination:      {}", transfer.to_address);
+    println!(" 3. Amount:           {}", transfer.token);
+    println!(" 4. Memo:             {}", if transfer.memo.is_empty() { "none" } else { &transfer.memo });
+    match &transfer.ibc {
+        Some(route) => {
+            println!(" 5. IBC Channel:      {}", route.channel);
+            match route.timeout_height {
+                Some((number, height)) => println!(" 6. Timeout Height:   {}-{}", number, height),
+                None => println!(" 6. Timeout Height:   none"),
+            }
+            match route.timeout_secs {
+                Some(secs) => println!(" 7. Timeout:          {} seconds\n", secs),
+                None => println!(" 7. Timeout:          none\n"),
+            }
+        }
+        None => println!(" 5. Network:          Osmosis\n"),
+    }
+
+    read_confirmation()
+}
+
+// Function to get user confirmation of a concentrated liquidity transaction (y/n)
+fn get_cl_confirmation(address: &str, balances: Vec<CoinAmount>, command: &ClCommands, pool_id: Option<u64>, deposits: &[DenomAmount]) -> bool {
+    let pool_id = pool_id.map_or("-".to_string(), |pool_id| pool_id.to_string());
+    let amounts = |amounts: [f64; 2]| {
+        deposits
+            .iter()
+            .zip(amounts)
+            .map(|(deposit, amount)| DenomAmount { denom: deposit.denom.clone(), amount: ((amount * 1_000_000.0) as u64).to_string() }.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    println!("\nPlease confirm the following details for the Concentrated Liquidity transaction:");
+    println!(" 1. Account Address: {}", address);
+
+    // Display account balances
+    println!("\n    Account Balances:");
+    for balance in &balances {
+        println!("    - {}", balance);
+    }
+
+    match command {
+        ClCommands::Create { lower_tick, upper_tick, amount0, amount1, min_amount0, min_amount1, .. } => {
+            println!("\n 2. Action:           Create a position on pool {}", pool_id);
+            println!(" 3. Tick Range:       [{}, {})", lower_tick, upper_tick);
+            println!(" 4. Deposit:          {}", amounts([*amount0, *amount1]));
+            println!(" 5. Min Deposit:      {}\n", amounts([*min_amount0, *min_amount1]));
+        }
+        ClCommands::AddTo { position_id, amount0, amount1, min_amount0, min_amount1 } => {
+            println!("\n 2. Action:           Add to position #{} on pool {}", position_id, pool_id);
+            println!(" 3. Deposit:          {}", amounts([*amount0, *amount1]));
+            println!(" 4. Min Deposit:      {}\n", amounts([*min_amount0, *min_amount1]));
+        }
+        ClCommands::Withdraw { position_id, share_pct } => {
+            println!("\n 2. Action:           Withdraw from position #{}", position_id);
+            println!(" 3. Share:            {}%\n", share_pct);
+        }
+        ClCommands::Collect { position_ids, incentives } => {
+            println!("\n 2. Action:           Collect {}", if *incentives { "spread rewards and incentives" } else { "spread rewards" });
+            println!(" 3. Positions:        {:?}\n", position_ids);
+        }
+        ClCommands::View { .. } => {}
+    }
+
+    read_confirmation()
+}
+
+// Function to get user confirmation of a pool liquidity transaction (y/n)
+fn get_lp_confirmation(address: &str, balances: Vec<CoinAmount>, command: &LpCommands, pool_id: u64, share_denom: &str, deposits: &[DenomAmount]) -> bool {
+    println!("\nPlease confirm the following details for the Liquidity Pool transaction:");
+    println!(" 1. Account Address: {}", address);
+
+    // Display account balances
+    println!("\n    Account Balances:");
+    for balance in &balances {
+        println!("    - {}", balance);
+    }
+
+    match command {
+        LpCommands::Join { max_slippage_bps, .. } => {
+            let deposits: Vec<String> = deposits.iter().map(|deposit| deposit.to_string()).collect();
+            println!("\n 2. Action:           Join pool {}", pool_id);
+            println!(" 3. Deposit:          {}", deposits.join(", "));
+            println!(" 4. Max Slippage:     {} bps\n", max_slippage_bps);
+        }
+        LpCommands::Exit { share_pct, max_slippage_bps, .. } => {
+            println!("\n 2. Action:           Exit pool {}", pool_id);
+            println!(" 3. Shares:           {}% of the {} held", share_pct, share_denom);
+            println!(" 4. Max Slippage:     {} bps\n", max_slippage_bps);
+        }
+    }
+
+    read_confirmation()
+}
+
+// Function to read a y/n answer, asks again on invalid input
+fn read_confirmation() -> bool {
     print!("Do you want to continue? (y/n): ");
     io::stdout().flush().unwrap(); // Ensures the prompt is displayed correctly
 
@@ -237,7 +1291,145 @@ fn get_user_confirmation(address: &str, balances: Vec<CoinAmount>, amount: u64,
         "n" => false,
         _ => {
             println!("Invalid input, please enter 'y' or 'n'");
-            get_user_confirmation(address, balances, amount, swap_type, daily_streams, min_price) // Recursively ask again on invalid input
+            read_confirmation() // Recursively ask again on invalid input
+        }
+    }
+}
+
+// Function to get the signer from the mnemonic prompt, exits on error
+fn signer_from_prompt() -> Signer {
+    // Get mnemonic from user
+    let mnemonic = match get_account_from_prompt("Osmosis") {
+        Ok(ret) => ret,
+        Err(e) => {
+            error!("Error getting account keys: {:?}", e);
+            std::process::exit(0);
+        }
+    };
+
+    // Create signer
+    match Signer::new(&mnemonic) {
+        Ok(ret) => ret,
+        Err(e) => {
+            error!("Error creating signer: {:?}", e);
+            std::process::exit(0);
+        }
+    }
+}
+
+// Function to stop when the account has a guard rail halt, it must be resumed explicitly
+fn exit_if_halted(address: &str) {
+    match guard_rails::active_halt(address) {
+        Ok(None) => {}
+        Ok(Some(halt)) => {
+            error!("Account halted by a guard rail at {}: {}", halt.halted_at, halt.violation);
+            println!("Run `resume --address {}` once the issue is addressed.", address);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            error!("Error reading guard rail halts: {:?}", e);
+            std::process::exit(0);
+        }
+    }
+}
+
+// Function to stop when the account holds less than the amounts it deposits
+async fn exit_if_insufficient(address: &str, deposits: &[DenomAmount]) {
+    for deposit in deposits {
+        let required: u128 = deposit.amount.parse().unwrap_or(0);
+        if required == 0 {
+            continue;
+        }
+        match fetch_denom_balance(address, &deposit.denom).await {
+            Ok(available) if available >= required => {}
+            Ok(available) => {
+                error!("Insufficient {} balance to deposit {}: {} available", deposit.denom, deposit, DenomAmount { denom: deposit.denom.clone(), amount: available.to_string() });
+                std::process::exit(0);
+            }
+            Err(e) => {
+                error!("Error fetching {} balance: {:?}", deposit.denom, e);
+                std::process::exit(0);
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+// Function to display the concentrated liquidity positions of an account, false when it has none
+async fn print_cl_positions(address: &str) -> bool {
+    let positions = match osmosis_cl_service::fetch_positions(address).await {
+        Ok(positions) => positions,
+        Err(e) => {
+            error!("Error fetching concentrated liquidity positions: {:?}", e);
+            return false;
+        }
+    };
+    if positions.is_empty() {
+        return false;
+    }
+
+    println!("\nConcentrated liquidity positions for account: {}", address);
+    for position in &positions {
+        println!("  {}", position);
+        let status = match osmosis_cl_service::fetch_cl_pool(position.pool_id).await {
+            Ok(pool) => position.status(&pool),
+            Err(e) => Err(e),
+        };
+        match status {
+            Ok(status) => println!(
+                "    value: {}, {}",
+                DenomAmount { denom: status.value_denom, amount: status.value.to_string() },
+                if status.in_range { "in range" } else { "out of range" },
+            ),
+            Err(e) => println!("    value: unavailable ({})", e),
+        }
+        let rewards: Vec<String> = position.spread_rewards.iter().chain(&position.incentives).map(|reward| reward.to_string()).collect();
+        if !rewards.is_empty() {
+            println!("    claimable: {}", rewards.join(", "));
+        }
+    }
+    true
+}
+
+// Function to display the shares held in the pools the account joined
+async fn print_lp_positions(address: &str) {
+    let pools = match joined_pools(address) {
+        Ok(pools) => pools,
+        Err(e) => {
+            error!("Error reading LP positions: {:?}", e);
+            return;
+        }
+    };
+    if pools.is_empty() {
+        return;
+    }
+
+    println!("\nLP positions for account: {}", address);
+    for (pool_id, share_denom) in pools {
+        match fetch_denom_balance(address, &share_denom).await {
+            Ok(shares) => println!("  pool {}: {} {}", pool_id, shares, share_denom),
+            Err(e) => println!("  pool {}: unavailable ({})", pool_id, e),
+        }
+    }
+}
+
+// Function to read the sweep policy from the config, exits on an invalid one
+fn sweep_policy_or_exit() -> Option<SweepPolicy> {
+    match SweepPolicy::from_config() {
+        Ok(policy) => policy,
+        Err(e) => {
+            error!("Invalid sweep config: {}", e);
+            std::process::exit(0);
+        }
+    }
+}
+
+// Function to fetch the account balances, exits on error
+async fn fetch_balances_or_exit(address: &str) -> Vec<CoinAmount> {
+    match fetch_balances(address, None).await {
+        Ok(balances) => balances,
+        Err(e) => {
+            error!("Error fetching account balances: {:?}", e);
+            std::process::exit(0);
+        }
+    }
+}
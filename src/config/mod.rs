@@ -13,12 +13,83 @@ pub struct Config {
     pub gas_config: GasConfig,
     pub env_constants: EnvConstants,
     pub osmosis_chain_id: String,
-    pub osmosis_status_url: String,
-    pub osmosis_account_info_url: String,
-    pub osmosis_broadcast_tx_url: String,
-    pub osmosis_pool_price_url: String,
-    pub osmosis_account_balances_url: String,
-    pub osmosis_tx_details_url: String,
+    pub osmosis_status_url: EndpointList,
+    pub osmosis_account_info_url: EndpointList,
+    pub osmosis_broadcast_tx_url: EndpointList,
+    pub osmosis_pool_price_url: EndpointList,
+    pub osmosis_account_balances_url: EndpointList,
+    pub osmosis_tx_details_url: EndpointList,
+    pub osmosis_cl_positions_url: EndpointList,
+    pub osmosis_tx_search_url: EndpointList,
+    #[serde(default)]
+    pub broadcast_config: BroadcastConfig,
+    #[serde(default)]
+    pub endpoint_config: EndpointConfig,
+    #[serde(default)]
+    pub price_validation: PriceValidationConfig,
+    #[serde(default)]
+    pub guard_rails: GuardRailsConfig,
+    #[serde(default)]
+    pub footprint: FootprintConfig,
+    #[serde(default)]
+    pub sweep: SweepConfig,
+    #[serde(default)]
+    pub gas_station: GasStationConfig,
+}
+
+/// One or more provider URLs for the same endpoint, accepts a single string or a list
+#[derive(Debug, Deserialize, Default)]
+#[serde(from = "OneOrMany")]
+pub struct EndpointList(Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for EndpointList {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(url) => EndpointList(vec![url]),
+            OneOrMany::Many(urls) => EndpointList(urls),
+        }
+    }
+}
+
+impl EndpointList {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct EndpointConfig {
+    /// consecutive failures before a provider circuit is opened
+    pub failure_threshold: u32,
+    /// seconds a provider is skipped once its circuit is open
+    pub cooldown_secs: u64,
+    /// seconds between provider health checks, 0 disables them
+    pub health_check_interval_secs: u64,
+    /// seconds a provider has to answer a request before the next one is tried
+    pub request_timeout_secs: u64,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        EndpointConfig {
+            failure_threshold: 3,
+            cooldown_secs: 60,
+            health_check_interval_secs: 300,
+            request_timeout_secs: 10,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +97,167 @@ pub struct GasConfig {
     pub token: Coin,
     pub amount: u64,
     pub gas_limit: u64,
+    /// pool between the gas token and token_out, in either order, values the fees of swaps that do not trade the gas token
+    pub price_pool_id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct BroadcastConfig {
+    /// how the node should answer the broadcast request
+    #[serde(default)]
+    pub mode: BroadcastMode,
+    /// which API is used to submit the transaction
+    #[serde(default)]
+    pub transport: BroadcastTransport,
+    /// how the inclusion of the transaction in a block is detected
+    #[serde(default)]
+    pub confirmation: ConfirmationMethod,
+    /// gRPC endpoints, required when transport = "grpc"
+    #[serde(default)]
+    pub grpc_url: EndpointList,
+    /// CometBFT websocket endpoint, required when confirmation = "websocket"
+    pub websocket_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastMode {
+    /// returns right after the tx is sent, without waiting for CheckTx
+    #[default]
+    Async,
+    /// waits for CheckTx, so mempool rejections are reported immediately
+    Sync,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastTransport {
+    #[default]
+    Rest,
+    Grpc,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmationMethod {
+    /// polls the LCD tx endpoint until the tx is found
+    #[default]
+    Poll,
+    /// subscribes to the CometBFT `Tx` event of the tx hash
+    Websocket,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PriceValidationConfig {
+    /// number of pool price providers that must answer before trading
+    pub min_sources: usize,
+    /// max spread between the pool quotes, in basis points
+    pub max_deviation_bps: u64,
+    /// independent price the pool quotes are compared against
+    pub reference: PriceReference,
+    /// max spread between the pool price and the reference, in basis points
+    pub max_reference_deviation_bps: u64,
+    /// Osmosis `ArithmeticTwapToNow` endpoint, required when reference = "twap"
+    pub twap_url: Option<String>,
+    /// seconds covered by the TWAP
+    pub twap_window_secs: i64,
+    /// JSON file with `price` and `updated_at` (unix seconds), required when reference = "oracle_file"
+    pub oracle_file: Option<PathBuf>,
+    /// max age of the oracle file price, in seconds
+    pub oracle_max_age_secs: u64,
+}
+
+impl Default for PriceValidationConfig {
+    fn default() -> Self {
+        PriceValidationConfig {
+            min_sources: 1,
+            max_deviation_bps: 100,
+            reference: PriceReference::None,
+            max_reference_deviation_bps: 500,
+            twap_url: None,
+            twap_window_secs: 3600,
+            oracle_file: None,
+            oracle_max_age_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceReference {
+    None,
+    Twap,
+    OracleFile,
+}
+
+/// Conditions that halt the stream until it is explicitly resumed, all optional
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct GuardRailsConfig {
+    /// halt buys of token_out when the price paid is above this value, in token_in per token_out
+    pub max_price: Option<f64>,
+    /// halt when a trade would leave less token_in than this, in microns
+    pub balance_floor: Option<u64>,
+    /// halt when the fees paid since midnight UTC reach this amount of the gas token, in microns
+    pub daily_fee_cap: Option<u64>,
+    /// price the realized average price of the pool is compared against
+    pub benchmark_price: Option<f64>,
+    /// max deviation of the realized average price from the benchmark, in percent
+    pub max_benchmark_deviation_pct: Option<f64>,
+}
+
+/// Variations of the transactions, so the stream is harder to fingerprint on chain
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct FootprintConfig {
+    /// memos picked at random for each transaction, "Trade Stream" when empty
+    pub memos: Vec<String>,
+    /// max random increase of the fee amount and gas limit, in percent
+    pub gas_jitter_pct: f64,
+}
+
+/// Cold wallet the stream proceeds are swept to, sweeping is off without destination and threshold
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct SweepConfig {
+    /// address the proceeds are sent to
+    pub destination: Option<String>,
+    /// token_out balance above which a sweep is sent, in microns
+    pub threshold: Option<u64>,
+    /// token_out balance kept in the streaming account, in microns
+    pub buffer: u64,
+}
+
+/// Buys the gas token before a trade when it runs low, off without token and pool
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct GasStationConfig {
+    /// top up when the gas balance covers fewer fees than this, defaults to 5
+    pub min_trades: u64,
+    /// fees worth of gas token bought per top up, defaults to 20
+    pub top_up_trades: u64,
+    /// token sold for gas, token_in or token_out
+    pub token: Option<Coin>,
+    /// pool between the token sold and the gas token
+    pub pool_id: Option<u64>,
+    /// min amount of gas token received per token sold
+    pub min_price: f64,
+    /// max amount of the token sold for gas per day (UTC), in microns
+    pub daily_spend_cap: Option<u64>,
+}
+
+impl Default for GasStationConfig {
+    fn default() -> Self {
+        GasStationConfig {
+            min_trades: 5,
+            top_up_trades: 20,
+            token: None,
+            pool_id: None,
+            min_price: 0.0,
+            daily_spend_cap: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,521 @@
+use std::io::Write;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use anyhow::{anyhow, Result};
+use crate::chains::coin::Coin;
+use crate::chains::osmosis::osmosis_transaction::all_transactions;
+
+/// Decimals of the amounts of unknown denoms, Osmosis tokens are usually in micro units
+const DEFAULT_DECIMALS: u32 = 6;
+
+/// File format of the ledger export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// comma separated values with a header row
+    Csv,
+    /// one JSON object per line
+    Jsonl,
+    /// Koinly universal CSV, for tax reports
+    Koinly,
+    /// CoinTracking CSV import, for tax reports
+    Cointracking,
+}
+
+/// Transactions of the ledger to export, all of them by default
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// transactions broadcasted at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// transactions broadcasted before this time
+    pub to: Option<DateTime<Utc>>,
+    pub account: Option<String>,
+    /// `broadcasted`, `executed`, `error` or `timeout`
+    pub status: Option<String>,
+}
+
+impl ExportFilter {
+    fn matches(&self, account_id: &str, tx: &Value, timestamp: Option<i64>) -> bool {
+        let in_range = match timestamp {
+            Some(timestamp) => {
+                self.from.is_none_or(|from| timestamp >= from.timestamp()) && self.to.is_none_or(|to| timestamp < to.timestamp())
+            }
+            None => self.from.is_none() && self.to.is_none(),
+        };
+        in_range
+            && self.account.as_ref().is_none_or(|account| account == account_id)
+            && self.status.as_ref().is_none_or(|status| tx["tx_status"] == status.as_str())
+    }
+}
+
+/// A ledger record flattened for spreadsheets. Amounts are in tokens with the decimals of
+/// their coin, for transfers `token_in` and `amount` are the token and amount sent.
+#[derive(Debug, Serialize)]
+pub struct ExportRow {
+    #[serde(skip)]
+    pub time: Option<DateTime<Utc>>,
+    pub account: String,
+    pub txhash: String,
+    /// ISO-8601 in UTC
+    pub timestamp: String,
+    /// `swap` or the action of the record
+    pub action: String,
+    pub tx_status: String,
+    pub status_code: Option<u64>,
+    pub pool_id: Option<u64>,
+    pub token_in: Option<String>,
+    pub token_out: Option<String>,
+    pub swap_type: Option<String>,
+    pub amount: Option<String>,
+    pub tokens_in: Option<String>,
+    pub tokens_out: Option<String>,
+    pub executed_price: Option<f64>,
+    pub fee_paid: Option<String>,
+    pub fee_denom: Option<String>,
+    /// fee valued in token_out
+    pub fee_value: Option<String>,
+    pub raw_log: Option<String>,
+}
+
+impl ExportRow {
+    fn from_record(account_id: &str, tx: &Value, timestamp: Option<i64>) -> Self {
+        let text = |key: &str| tx[key].as_str().map(String::from);
+        let coin = |key: &str| tx[key].as_str().and_then(|name| name.parse::<Coin>().ok());
+        let decimal = |key: &str, coin: Option<Coin>| {
+            tx[key].as_u64().map(|amount| format_decimal(amount, coin.map_or(DEFAULT_DECIMALS, |coin| coin.decimals())))
+        };
+        let time = timestamp.and_then(|timestamp| DateTime::from_timestamp(timestamp, 0));
+        let token_in = coin("token_in").or_else(|| coin("token"));
+        let token_out = coin("token_out");
+        let fee_coin = tx["fee_denom"].as_str().and_then(Coin::from_denom);
+
+        ExportRow {
+            time,
+            account: account_id.to_string(),
+            txhash: text("txhash").unwrap_or_default(),
+            timestamp: time
+                .map(|datetime| datetime.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default(),
+            action: text("action").unwrap_or_else(|| "swap".to_string()),
+            tx_status: text("tx_status").unwrap_or_default(),
+            status_code: tx["status_code"].as_u64(),
+            pool_id: tx["pool_id"].as_u64(),
+            token_in: text("token_in").or_else(|| text("token")),
+            token_out: text("token_out"),
+            swap_type: text("swap_type"),
+            amount: decimal("amount", token_in),
+            tokens_in: decimal("tokens_in", token_in),
+            tokens_out: decimal("tokens_out", token_out),
+            executed_price: tx["executed_price"].as_f64(),
+            fee_paid: decimal("fee_paid", fee_coin),
+            fee_denom: text("fee_denom"),
+            fee_value: decimal("fee_value", token_out),
+            raw_log: text("raw_log").filter(|raw_log| !raw_log.is_empty()),
+        }
+    }
+}
+
+/// Rows of the ledger that match the filter, oldest first
+pub fn export_rows(filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+    let accounts = all_transactions().map_err(|e| anyhow!("Failed to read transactions: {}", e))?;
+
+    let mut rows = Vec::new();
+    for (account_id, records) in &accounts {
+        for tx in records.as_array().into_iter().flatten() {
+            let timestamp = tx["timestamp"].as_str().and_then(|t| t.parse::<i64>().ok());
+            if filter.matches(account_id, tx, timestamp) {
+                rows.push((timestamp, ExportRow::from_record(account_id, tx, timestamp)));
+            }
+        }
+    }
+    rows.sort_by_key(|(timestamp, _)| *timestamp);
+
+    Ok(rows.into_iter().map(|(_, row)| row).collect())
+}
+
+/// Writes the rows in the given format
+pub fn write_rows<W: Write>(rows: &[ExportRow], format: ExportFormat, mut writer: W) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Jsonl => {
+            for row in rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Koinly | ExportFormat::Cointracking => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for line in rows.iter().filter_map(TaxLine::from_row) {
+                match format {
+                    ExportFormat::Koinly => writer.serialize(KoinlyRow::from(&line))?,
+                    _ => writer.serialize(CoinTrackingRow::from(&line))?,
+                }
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// What a tax line records
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TaxKind {
+    /// a swap, token_in sent and token_out received
+    Trade,
+    /// a transfer out of the account
+    Withdrawal,
+    /// the fee of a transaction that moved nothing, or failed once included in a block
+    Fee,
+}
+
+/// A transaction as seen by tax software, amounts as written in the export
+struct TaxLine<'a> {
+    kind: TaxKind,
+    time: DateTime<Utc>,
+    sent: Option<(&'a str, &'a str)>,
+    received: Option<(&'a str, &'a str)>,
+    fee: Option<(&'a str, String)>,
+    description: String,
+    txhash: &'a str,
+}
+
+impl<'a> TaxLine<'a> {
+    /// Line of a ledger row, none for transactions that were not included in a block.
+    /// Liquidity actions are reported as fees, their token movements are not mapped.
+    fn from_row(row: &'a ExportRow) -> Option<Self> {
+        if row.tx_status != "executed" {
+            return None;
+        }
+        let time = row.time?;
+        let fee = match (row.fee_paid.as_deref(), row.fee_denom.as_deref()) {
+            (Some(amount), Some(denom)) => {
+                let currency = Coin::from_denom(denom).map_or_else(|| denom.to_string(), |coin| coin.to_string());
+                Some((amount, currency))
+            }
+            _ => None,
+        };
+
+        let executed = row.status_code == Some(0);
+        let (kind, sent, received) = match (row.action.as_str(), executed) {
+            ("swap" | "gas_top_up", true) => {
+                let sent = row.tokens_in.as_deref().zip(row.token_in.as_deref());
+                let received = row.tokens_out.as_deref().zip(row.token_out.as_deref());
+                (TaxKind::Trade, Some(sent?), Some(received?))
+            }
+            ("send" | "ibc_transfer" | "sweep", true) => {
+                (TaxKind::Withdrawal, Some(row.amount.as_deref().zip(row.token_in.as_deref())?), None)
+            }
+            _ => (TaxKind::Fee, None, None),
+        };
+        if kind == TaxKind::Fee && fee.is_none() {
+            return None;
+        }
+
+        let description = match (kind, executed) {
+            (_, false) => format!("{} failed with code {}", row.action, row.status_code.unwrap_or_default()),
+            (TaxKind::Trade, true) if row.action == "swap" => match row.pool_id {
+                Some(pool_id) => format!("swap on pool {}", pool_id),
+                None => "swap".to_string(),
+            },
+            _ => row.action.clone(),
+        };
+
+        Some(TaxLine { kind, time, sent, received, fee, description, txhash: &row.txhash })
+    }
+}
+
+/// Row of the Koinly universal CSV
+#[derive(Serialize)]
+struct KoinlyRow<'a> {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Sent Amount")]
+    sent_amount: Option<&'a str>,
+    #[serde(rename = "Sent Currency")]
+    sent_currency: Option<&'a str>,
+    #[serde(rename = "Received Amount")]
+    received_amount: Option<&'a str>,
+    #[serde(rename = "Received Currency")]
+    received_currency: Option<&'a str>,
+    #[serde(rename = "Fee Amount")]
+    fee_amount: Option<&'a str>,
+    #[serde(rename = "Fee Currency")]
+    fee_currency: Option<&'a str>,
+    #[serde(rename = "Net Worth Amount")]
+    net_worth_amount: Option<&'a str>,
+    #[serde(rename = "Net Worth Currency")]
+    net_worth_currency: Option<&'a str>,
+    #[serde(rename = "Label")]
+    label: &'a str,
+    #[serde(rename = "Description")]
+    description: &'a str,
+    #[serde(rename = "TxHash")]
+    txhash: &'a str,
+}
+
+impl<'a> From<&'a TaxLine<'a>> for KoinlyRow<'a> {
+    fn from(line: &'a TaxLine<'a>) -> Self {
+        // A fee without transfer is sent as a cost
+        let (sent, label) = match line.kind {
+            TaxKind::Fee => (line.fee.as_ref().map(|(amount, currency)| (*amount, currency.as_str())), "cost"),
+            _ => (line.sent, ""),
+        };
+        let fee = match line.kind {
+            TaxKind::Fee => None,
+            _ => line.fee.as_ref(),
+        };
+
+        KoinlyRow {
+            date: line.time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            sent_amount: sent.map(|(amount, _)| amount),
+            sent_currency: sent.map(|(_, currency)| currency),
+            received_amount: line.received.map(|(amount, _)| amount),
+            received_currency: line.received.map(|(_, currency)| currency),
+            fee_amount: fee.map(|(amount, _)| *amount),
+            fee_currency: fee.map(|(_, currency)| currency.as_str()),
+            net_worth_amount: None,
+            net_worth_currency: None,
+            label,
+            description: &line.description,
+            txhash: line.txhash,
+        }
+    }
+}
+
+/// Row of the CoinTracking CSV import
+#[derive(Serialize)]
+struct CoinTrackingRow<'a> {
+    #[serde(rename = "Type")]
+    kind: &'a str,
+    #[serde(rename = "Buy Amount")]
+    buy_amount: Option<&'a str>,
+    #[serde(rename = "Buy Currency")]
+    buy_currency: Option<&'a str>,
+    #[serde(rename = "Sell Amount")]
+    sell_amount: Option<&'a str>,
+    #[serde(rename = "Sell Currency")]
+    sell_currency: Option<&'a str>,
+    #[serde(rename = "Fee")]
+    fee: Option<&'a str>,
+    #[serde(rename = "Fee Currency")]
+    fee_currency: Option<&'a str>,
+    #[serde(rename = "Exchange")]
+    exchange: &'a str,
+    #[serde(rename = "Trade-Group")]
+    trade_group: &'a str,
+    #[serde(rename = "Comment")]
+    comment: &'a str,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Tx-ID")]
+    txhash: &'a str,
+}
+
+impl<'a> From<&'a TaxLine<'a>> for CoinTrackingRow<'a> {
+    fn from(line: &'a TaxLine<'a>) -> Self {
+        // A fee without transfer is sold as an other fee
+        let (kind, sell) = match line.kind {
+            TaxKind::Trade => ("Trade", line.sent),
+            TaxKind::Withdrawal => ("Withdrawal", line.sent),
+            TaxKind::Fee => ("Other Fee", line.fee.as_ref().map(|(amount, currency)| (*amount, currency.as_str()))),
+        };
+        let fee = match line.kind {
+            TaxKind::Fee => None,
+            _ => line.fee.as_ref(),
+        };
+
+        CoinTrackingRow {
+            kind,
+            buy_amount: line.received.map(|(amount, _)| amount),
+            buy_currency: line.received.map(|(_, currency)| currency),
+            sell_amount: sell.map(|(amount, _)| amount),
+            sell_currency: sell.map(|(_, currency)| currency),
+            fee: fee.map(|(amount, _)| *amount),
+            fee_currency: fee.map(|(_, currency)| currency.as_str()),
+            exchange: "Osmosis",
+            trade_group: "",
+            comment: &line.description,
+            date: line.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            txhash: line.txhash,
+        }
+    }
+}
+
+// Formats an on-chain amount as a plain decimal, without thousands separators
+fn format_decimal(amount: u64, decimals: u32) -> String {
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    let unit = 10u64.pow(decimals);
+    format!("{}.{:0width$}", amount / unit, amount % unit, width = decimals as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 2024-01-01T00:00:00Z
+    const NEW_YEAR: i64 = 1_704_067_200;
+
+    fn swap(txhash: &str, timestamp: i64, status_code: u64) -> (Value, i64) {
+        let tx = json!({
+            "txhash": txhash,
+            "timestamp": timestamp.to_string(),
+            "tx_status": "executed",
+            "status_code": status_code,
+            "pool_id": 1,
+            "token_in": "OSMO",
+            "token_out": "USDC",
+            "swap_type": "amount_in",
+            "amount": 10_000_000,
+            "tokens_in": 10_000_000,
+            "tokens_out": 5_000_000,
+            "executed_price": 0.5,
+            "fee_paid": 2_500,
+            "fee_denom": "uosmo",
+            "fee_value": 1_250,
+            "raw_log": "",
+        });
+        (tx, timestamp)
+    }
+
+    fn send(txhash: &str, timestamp: i64) -> (Value, i64) {
+        let tx = json!({
+            "txhash": txhash,
+            "timestamp": timestamp.to_string(),
+            "action": "send",
+            "tx_status": "executed",
+            "status_code": 0,
+            "token": "OSMO",
+            "amount": 2_000_000,
+            "fee_paid": 1_000,
+            "fee_denom": "uosmo",
+        });
+        (tx, timestamp)
+    }
+
+    fn row((tx, timestamp): (Value, i64)) -> ExportRow {
+        ExportRow::from_record("osmo1account", &tx, Some(timestamp))
+    }
+
+    #[test]
+    fn amounts_are_formatted_with_the_given_decimals() {
+        assert_eq!(format_decimal(1_500_000, 6), "1.500000");
+        assert_eq!(format_decimal(5, 6), "0.000005");
+        assert_eq!(format_decimal(12_345, 2), "123.45");
+        assert_eq!(format_decimal(1_234, 0), "1234");
+    }
+
+    #[test]
+    fn records_are_flattened_with_iso_timestamps_and_token_amounts() {
+        let swap = row(swap("SWAP", NEW_YEAR, 0));
+        assert_eq!(swap.timestamp, "2024-01-01T00:00:00Z");
+        assert_eq!(swap.action, "swap");
+        assert_eq!(swap.tokens_in.as_deref(), Some("10.000000"));
+        assert_eq!(swap.tokens_out.as_deref(), Some("5.000000"));
+        assert_eq!(swap.fee_paid.as_deref(), Some("0.002500"));
+        assert_eq!(swap.fee_value.as_deref(), Some("0.001250"));
+        assert_eq!(swap.raw_log, None);
+
+        let send = row(send("SEND", NEW_YEAR));
+        assert_eq!(send.action, "send");
+        assert_eq!(send.token_in.as_deref(), Some("OSMO"));
+        assert_eq!(send.amount.as_deref(), Some("2.000000"));
+    }
+
+    #[test]
+    fn records_are_filtered_by_range_account_and_status() {
+        let (tx, timestamp) = swap("SWAP", NEW_YEAR, 0);
+        let filter = ExportFilter {
+            from: DateTime::from_timestamp(NEW_YEAR, 0),
+            to: DateTime::from_timestamp(NEW_YEAR + 1, 0),
+            account: Some("osmo1account".to_string()),
+            status: Some("executed".to_string()),
+        };
+        assert!(filter.matches("osmo1account", &tx, Some(timestamp)));
+        assert!(!filter.matches("osmo1other", &tx, Some(timestamp)));
+        assert!(!filter.matches("osmo1account", &tx, Some(timestamp + 1)));
+        assert!(!filter.matches("osmo1account", &tx, None));
+        assert!(ExportFilter::default().matches("osmo1account", &tx, None));
+    }
+
+    fn tax_export(records: Vec<(Value, i64)>, format: ExportFormat) -> String {
+        let rows: Vec<ExportRow> = records.into_iter().map(row).collect();
+        let mut output = Vec::new();
+        write_rows(&rows, format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn tax_lines_map_trades_withdrawals_and_fees() {
+        let trade = row(swap("SWAP", NEW_YEAR, 0));
+        let line = TaxLine::from_row(&trade).unwrap();
+        assert_eq!(line.kind, TaxKind::Trade);
+        assert_eq!(line.sent, Some(("10.000000", "OSMO")));
+        assert_eq!(line.received, Some(("5.000000", "USDC")));
+        assert_eq!(line.fee, Some(("0.002500", "OSMO".to_string())));
+        assert_eq!(line.description, "swap on pool 1");
+
+        let withdrawal = row(send("SEND", NEW_YEAR));
+        let line = TaxLine::from_row(&withdrawal).unwrap();
+        assert_eq!(line.kind, TaxKind::Withdrawal);
+        assert_eq!(line.sent, Some(("2.000000", "OSMO")));
+        assert_eq!(line.received, None);
+
+        // Only the fee of a failed tx left the account
+        let failed = row(swap("FAILED", NEW_YEAR, 11));
+        let line = TaxLine::from_row(&failed).unwrap();
+        assert_eq!(line.kind, TaxKind::Fee);
+        assert_eq!((line.sent, line.received), (None, None));
+        assert_eq!(line.description, "swap failed with code 11");
+
+        let (mut join, timestamp) = send("JOIN", NEW_YEAR);
+        join["action"] = json!("lp_join");
+        let join = row((join, timestamp));
+        assert_eq!(TaxLine::from_row(&join).unwrap().kind, TaxKind::Fee);
+    }
+
+    #[test]
+    fn tax_lines_skip_txs_that_moved_nothing() {
+        let (mut pending, timestamp) = swap("PENDING", NEW_YEAR, 0);
+        pending["tx_status"] = json!("broadcasted");
+        assert!(TaxLine::from_row(&row((pending, timestamp))).is_none());
+
+        let (mut feeless, timestamp) = swap("FEELESS", NEW_YEAR, 11);
+        feeless.as_object_mut().unwrap().remove("fee_paid");
+        assert!(TaxLine::from_row(&row((feeless, timestamp))).is_none());
+    }
+
+    #[test]
+    fn koinly_export_matches_the_universal_csv() {
+        let records = vec![swap("SWAP", NEW_YEAR, 0), send("SEND", NEW_YEAR + 3_600), swap("FAILED", NEW_YEAR + 7_200, 11)];
+        assert_eq!(
+            tax_export(records, ExportFormat::Koinly),
+            "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash\n\
+             2024-01-01 00:00:00 UTC,10.000000,OSMO,5.000000,USDC,0.002500,OSMO,,,,swap on pool 1,SWAP\n\
+             2024-01-01 01:00:00 UTC,2.000000,OSMO,,,0.001000,OSMO,,,,send,SEND\n\
+             2024-01-01 02:00:00 UTC,0.002500,OSMO,,,,,,,cost,swap failed with code 11,FAILED\n"
+        );
+    }
+
+    #[test]
+    fn cointracking_export_matches_the_csv_import() {
+        let records = vec![swap("SWAP", NEW_YEAR, 0), send("SEND", NEW_YEAR + 3_600), swap("FAILED", NEW_YEAR + 7_200, 11)];
+        assert_eq!(
+            tax_export(records, ExportFormat::Cointracking),
+            "Type,Buy Amount,Buy Currency,Sell Amount,Sell Currency,Fee,Fee Currency,Exchange,Trade-Group,Comment,Date,Tx-ID\n\
+             Trade,5.000000,USDC,10.000000,OSMO,0.002500,OSMO,Osmosis,,swap on pool 1,2024-01-01 00:00:00,SWAP\n\
+             Withdrawal,,,2.000000,OSMO,0.001000,OSMO,Osmosis,,send,2024-01-01 01:00:00,SEND\n\
+             Other Fee,,,0.002500,OSMO,,,Osmosis,,swap failed with code 11,2024-01-01 02:00:00,FAILED\n"
+        );
+    }
+}
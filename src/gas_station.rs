@@ -0,0 +1,70 @@
+use chrono::Utc;
+use log::{info, warn};
+use anyhow::{anyhow, Result};
+use crate::config::CONFIG;
+use crate::chains::coin::CoinAmount;
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_pool_service::{self, SwapOrder};
+use crate::chains::osmosis::osmosis_transaction::account_transactions;
+use crate::chains::osmosis::osmosis_tx_builder::FeeStrategy;
+use crate::trade_outcome::TradeOutcome;
+
+/// Buys the gas token when the balance covers fewer than `min_trades` fees,
+/// none when it is not needed, not configured or the daily spend cap is reached.
+/// Fees are counted at the max jittered fee of a swap, the balance a trade needs,
+/// so a trade is never skipped for gas while the station could still top up.
+pub async fn top_up_if_needed(signer: &Signer, gas_available: u64) -> Result<Option<TradeOutcome>> {
+    let config = &CONFIG.gas_station;
+    let (Some(token), Some(pool_id)) = (config.token, config.pool_id) else {
+        return Ok(None);
+    };
+    let fee = FeeStrategy::Jittered.max_amount();
+    if gas_available >= config.min_trades.max(1) * fee {
+        return Ok(None);
+    }
+    if config.min_price <= 0.0 {
+        return Err(anyhow!("The gas station needs a positive min_price"));
+    }
+
+    // 1. Keep the worst case spend within the daily cap
+    let amount = config.top_up_trades * fee;
+    let max_spend = (amount as f64 / config.min_price) as u64;
+    if let Some(cap) = config.daily_spend_cap {
+        let spent_today = spent_today(signer.get_account_address())?;
+        if spent_today + max_spend > cap {
+            warn!("!!! Gas station daily spend cap of {} reached, {} spent today", CoinAmount { coin: token, amount: cap }, CoinAmount { coin: token, amount: spent_today });
+            return Ok(None);
+        }
+    }
+
+    // 2. Buy an exact amount of the gas token
+    let gas_token = CONFIG.gas_config.token;
+    info!(">>> Gas balance {} is low, buying {} with at most {}", CoinAmount { coin: gas_token, amount: gas_available }, CoinAmount { coin: gas_token, amount }, CoinAmount { coin: token, amount: max_spend });
+    let order = SwapOrder {
+        pool_id,
+        coin_in: token,
+        coin_out: gas_token,
+        amount,
+        swap_type: "amount_out",
+        min_price: config.min_price,
+        max_slippage_bps: None,
+        ladder_tier: None,
+        rebalance: false,
+        market_side: None,
+        action: Some("gas_top_up"),
+    };
+    osmosis_pool_service::perform_swap(signer, &order).await.map(Some)
+}
+
+// Amount of token sold by executed gas top ups since midnight UTC
+fn spent_today(account_id: &str) -> Result<u64> {
+    let midnight = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+    let transactions = account_transactions(account_id).map_err(|e| anyhow!("Failed to read transactions: {}", e))?;
+
+    Ok(transactions
+        .iter()
+        .filter(|tx| tx["action"] == "gas_top_up" && tx["tx_status"] == "executed" && tx["status_code"].as_u64() == Some(0))
+        .filter(|tx| tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()).is_some_and(|t| t >= midnight))
+        .filter_map(|tx| tx["tokens_in"].as_u64())
+        .sum())
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::Utc;
+use anyhow::{anyhow, Result};
+use crate::config::{CONFIG, get_config_path};
+use crate::chains::coin::Coin;
+use crate::chains::osmosis::osmosis_transaction::account_transactions;
+
+/// A configured guard rail that was crossed, the stream stays halted until resumed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuardRailViolation {
+    PriceAboveMax { price: f64, max_price: f64 },
+    BalanceBelowFloor { coin: Coin, balance_after: u64, floor: u64 },
+    DailyFeeCap { spent: u64, cap: u64 },
+    BenchmarkDeviation { average_price: f64, benchmark_price: f64, deviation_pct: f64, max_deviation_pct: f64 },
+}
+
+/// A triggered halt, as stored in the halts file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HaltRecord {
+    pub violation: GuardRailViolation,
+    /// unix seconds
+    pub halted_at: String,
+    /// unix seconds, none while the stream is halted
+    pub resumed_at: Option<String>,
+}
+
+/// Halts buys of the configured token_out when the price paid per unit bought is above the
+/// configured ceiling. `price` is the amount of `coin_out` received per token sold.
+pub fn check_price(coin_out: Coin, price: f64) -> Option<GuardRailViolation> {
+    let max_price = CONFIG.guard_rails.max_price?;
+    price_above_max(coin_out == CONFIG.env_constants.token_out, price, max_price)
+}
+
+// A buy pays the inverse of the price received, in token_in per token_out
+fn price_above_max(buys: bool, price: f64, max_price: f64) -> Option<GuardRailViolation> {
+    let paid = 1.0 / price;
+    if buys && paid > max_price {
+        return Some(GuardRailViolation::PriceAboveMax { price: paid, max_price });
+    }
+    None
+}
+
+/// Halts when spending `required` would leave less than the configured floor
+pub fn check_balance(coin: Coin, available: u64, required: u64) -> Option<GuardRailViolation> {
+    let floor = CONFIG.guard_rails.balance_floor?;
+    let balance_after = available.saturating_sub(required);
+    if balance_after < floor {
+        return Some(GuardRailViolation::BalanceBelowFloor { coin, balance_after, floor });
+    }
+    None
+}
+
+/// Checks the fees paid today and the realized average price against the ledger
+pub fn check_ledger(account_id: &str, pool_id: u64) -> Result<Option<GuardRailViolation>> {
+    let config = &CONFIG.guard_rails;
+    let transactions = account_transactions(account_id).map_err(|e| anyhow!("Failed to read transactions: {}", e))?;
+
+    // 1. Fees, every tx included in a block paid its fee, whatever its code
+    if let Some(cap) = config.daily_fee_cap {
+        let midnight = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+        // Records older than the fee field paid the configured fee
+        let spent = transactions
+            .iter()
+            .filter(|tx| tx["tx_status"] == "executed")
+            .filter(|tx| tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()).is_some_and(|t| t >= midnight))
+            .map(|tx| tx["fee_paid"].as_u64().unwrap_or(CONFIG.gas_config.amount))
+            .sum::<u64>();
+        if spent >= cap {
+            return Ok(Some(GuardRailViolation::DailyFeeCap { spent, cap }));
+        }
+    }
+
+    // 2. Realized average price of the streamed swaps, market making and swaps
+    // the other way are quoted differently and would skew the average, and swaps
+    // imported by reconcile were not sent by the stream
+    if let (Some(benchmark_price), Some(max_deviation_pct)) = (config.benchmark_price, config.max_benchmark_deviation_pct) {
+        let (token_in, token_out) = (CONFIG.env_constants.token_in.to_string(), CONFIG.env_constants.token_out.to_string());
+        let executed: Vec<&Value> = transactions
+            .iter()
+            .filter(|tx| tx["pool_id"].as_u64() == Some(pool_id) && tx["action"].is_null() && tx["tx_status"] == "executed" && tx["status_code"].as_u64() == Some(0))
+            .filter(|tx| tx["token_in"] == token_in.as_str() && tx["token_out"] == token_out.as_str() && tx["market_side"].is_null() && tx["imported"] != true)
+            .collect();
+        let tokens_in: u64 = executed.iter().filter_map(|tx| tx["tokens_in"].as_u64()).sum();
+        let tokens_out: u64 = executed.iter().filter_map(|tx| tx["tokens_out"].as_u64()).sum();
+        if tokens_in > 0 {
+            let average_price = tokens_out as f64 / tokens_in as f64;
+            let deviation_pct = ((average_price - benchmark_price) / benchmark_price).abs() * 100.0;
+            if deviation_pct > max_deviation_pct {
+                return Ok(Some(GuardRailViolation::BenchmarkDeviation { average_price, benchmark_price, deviation_pct, max_deviation_pct }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Stores a triggered halt for the account
+pub fn record_halt(account_id: &str, violation: &GuardRailViolation) -> Result<()> {
+    let mut halts = read_halts()?;
+    let record = HaltRecord {
+        violation: violation.clone(),
+        halted_at: Utc::now().timestamp().to_string(),
+        resumed_at: None,
+    };
+    halts[account_id] = match halts[account_id].take() {
+        Value::Array(mut records) => {
+            records.push(serde_json::to_value(record)?);
+            Value::Array(records)
+        }
+        _ => Value::Array(vec![serde_json::to_value(record)?]),
+    };
+    write_halts(&halts)
+}
+
+/// The halt that keeps the account from streaming, if any
+pub fn active_halt(account_id: &str) -> Result<Option<HaltRecord>> {
+    let halts = read_halts()?;
+    let records: Vec<HaltRecord> = match &halts[account_id] {
+        Value::Array(records) => records.iter().cloned().map(serde_json::from_value).collect::<Result<_, _>>()?,
+        _ => Vec::new(),
+    };
+    Ok(records.into_iter().rev().find(|record| record.resumed_at.is_none()))
+}
+
+/// Marks the halts of the account as resumed, returns how many were active
+pub fn resume(account_id: &str) -> Result<usize> {
+    let mut halts = read_halts()?;
+    let now = Utc::now().timestamp().to_string();
+    let mut resumed = 0;
+    if let Some(records) = halts.get_mut(account_id).and_then(Value::as_array_mut) {
+        for record in records.iter_mut().filter(|record| record["resumed_at"].is_null()) {
+            record["resumed_at"] = Value::String(now.clone());
+            resumed += 1;
+        }
+    }
+    write_halts(&halts)?;
+    Ok(resumed)
+}
+
+fn read_halts() -> Result<Value> {
+    match fs::read_to_string(get_halts_file_path()) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(_) => Ok(Value::Object(serde_json::Map::new())), // No halts yet
+    }
+}
+
+fn write_halts(halts: &Value) -> Result<()> {
+    fs::write(get_halts_file_path(), serde_json::to_string_pretty(halts)?)?;
+    Ok(())
+}
+
+fn get_halts_file_path() -> PathBuf {
+    get_config_path().join("guard_rail_halts.json")
+}
+
+impl fmt::Display for GuardRailViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardRailViolation::PriceAboveMax { price, max_price } => write!(f, "price paid {} is above max price {}", price, max_price),
+            GuardRailViolation::BalanceBelowFloor { coin, balance_after, floor } => write!(f, "{} balance would fall to {}, below the floor of {}", coin, balance_after, floor),
+            GuardRailViolation::DailyFeeCap { spent, cap } => write!(f, "fees paid today {} reached the daily cap of {}", spent, cap),
+            GuardRailViolation::BenchmarkDeviation { average_price, benchmark_price, deviation_pct, max_deviation_pct } => write!(f, "average price {} deviates {:.2}% from benchmark {}, max allowed is {}%", average_price, deviation_pct, benchmark_price, max_deviation_pct),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_above_max_halts_expensive_buys() {
+        // 0.4 token_out received per token_in, 2.5 token_in paid per token_out
+        match price_above_max(true, 0.4, 2.0) {
+            Some(GuardRailViolation::PriceAboveMax { price, max_price }) => {
+                assert_eq!(price, 2.5);
+                assert_eq!(max_price, 2.0);
+            }
+            other => panic!("expected a max price halt, got {:?}", other),
+        }
+        // 0.5 token_out per token_in, 2.0 paid is not above the ceiling
+        assert!(price_above_max(true, 0.5, 2.0).is_none());
+        assert!(price_above_max(true, 0.8, 2.0).is_none());
+    }
+
+    #[test]
+    fn price_above_max_ignores_sales_of_token_out() {
+        // Selling token_out for token_in is not a buy, whatever the price
+        assert!(price_above_max(false, 0.4, 2.0).is_none());
+        assert!(price_above_max(false, 5.0, 2.0).is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A price tier of a ladder stream, with its own daily allocation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LadderTier {
+    /// daily amount allocated to this tier, in microns
+    pub daily_amount: u64,
+    /// the tier only trades when the price is at or above this value
+    pub min_price: f64,
+}
+
+/// Tiers parsed from the command line, formatted as `<amount>@<min_price>,...`
+/// e.g. `200@0.10,300@0.12,500@0.15`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ladder(pub Vec<LadderTier>);
+
+impl FromStr for Ladder {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut tiers = Vec::new();
+
+        for tier in input.split(',') {
+            let (amount, min_price) = tier
+                .trim()
+                .split_once('@')
+                .ok_or_else(|| format!("Invalid tier '{}', expected <amount>@<min_price>", tier))?;
+            let amount: f64 = amount.trim().parse().map_err(|e| format!("Invalid amount in tier '{}': {}", tier, e))?;
+            let min_price: f64 = min_price.trim().parse().map_err(|e| format!("Invalid min price in tier '{}': {}", tier, e))?;
+            if amount <= 0.0 || min_price <= 0.0 {
+                return Err(format!("Amount and min price of tier '{}' must be positive", tier));
+            }
+
+            tiers.push(LadderTier {
+                daily_amount: (amount * 1_000_000.0) as u64,
+                min_price,
+            });
+        }
+
+        // Lowest price first, so reachable tiers are filled in order
+        tiers.sort_by(|a, b| a.min_price.total_cmp(&b.min_price));
+
+        Ok(Ladder(tiers))
+    }
+}
+
+impl fmt::Display for LadderTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ >= {}", self.daily_amount as f64 / 1_000_000.0, self.min_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_are_parsed_in_microns_and_sorted_by_price() {
+        let ladder: Ladder = "300@0.12, 200.5@0.10,500@0.15".parse().unwrap();
+        assert_eq!(
+            ladder.0,
+            vec![
+                LadderTier { daily_amount: 200_500_000, min_price: 0.10 },
+                LadderTier { daily_amount: 300_000_000, min_price: 0.12 },
+                LadderTier { daily_amount: 500_000_000, min_price: 0.15 },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_tiers_are_rejected() {
+        assert!("200".parse::<Ladder>().is_err());
+        assert!("abc@0.10".parse::<Ladder>().is_err());
+        assert!("200@".parse::<Ladder>().is_err());
+        assert!("0@0.10".parse::<Ladder>().is_err());
+        assert!("200@-0.10".parse::<Ladder>().is_err());
+        assert!("200@0.10,".parse::<Ladder>().is_err());
+    }
+}
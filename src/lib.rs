@@ -5,4 +5,17 @@ pub mod streamer;
 pub mod key_manager;
 pub mod config;
 pub mod poll_service;
-pub mod trade_service;
\ No newline at end of file
+pub mod trade_service;
+pub mod price_service;
+pub mod trade_outcome;
+pub mod ladder;
+pub mod campaign;
+pub mod guard_rails;
+pub mod schedule;
+pub mod market_maker;
+pub mod rebalance;
+pub mod sweep;
+pub mod gas_station;
+pub mod pnl;
+pub mod export;
+pub mod period_summary;
\ No newline at end of file
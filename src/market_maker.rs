@@ -0,0 +1,257 @@
+use std::io::{self, Write};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::watch;
+use tokio::task::LocalSet;
+use tokio::time::{sleep, Duration};
+use chrono::{DateTime, Utc, Duration as ChronoDuration};
+use log::{info, warn, error};
+use anyhow::Result;
+use crate::config::CONFIG;
+use crate::chains::coin::CoinAmount;
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_account_service::fetch_balances;
+use crate::chains::osmosis::osmosis_endpoint_service::run_health_checks;
+use crate::chains::osmosis::osmosis_pool_service::{self, SwapOrder};
+use crate::chains::osmosis::osmosis_transaction::{account_transactions, record_unsent_outcome};
+use crate::price_service::{fetch_reference_price, fetch_validated_price};
+use crate::trade_service::available_balance;
+use crate::trade_outcome::{NextAction, SkipReason, TradeOutcome};
+use crate::guard_rails;
+
+const POLL_INTERVAL: u64 = 1000; // in milliseconds
+
+/// Side of a market making trade, for the configured token_in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketSide {
+    /// token_out -> token_in, when the pool price is below the band
+    Buy,
+    /// token_in -> token_out, when the pool price is above the band
+    Sell,
+}
+
+/// Balance limits of a token, in microns
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InventoryBounds {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+/// Two-sided stream that trades the pool back into a band around a reference price
+pub struct MarketMaker {
+    /// fixed reference price, the configured TWAP or oracle reference when none
+    pub reference_price: Option<f64>,
+    /// half width of the band around the reference, in basis points
+    pub spread_bps: u64,
+    /// amount of token_in bought or sold per trade, in microns
+    pub trade_amount: u64,
+    /// max amount of token_in bought and sold per day, in microns
+    pub daily_volume_cap: Option<u64>,
+    pub inventory_in: InventoryBounds,
+    pub inventory_out: InventoryBounds,
+    /// seconds between two price checks
+    pub interval_secs: u64,
+    /// max slippage from the quote taken before signing, in basis points
+    pub max_slippage_bps: Option<u64>,
+}
+
+impl MarketMaker {
+    pub async fn start(&self, signer: &Signer) {
+        // Create a LocalSet to run !Send futures on the current thread
+        let local = LocalSet::new();
+
+        // Keep provider latencies and circuit breakers up to date in the background
+        let health_checks = tokio::spawn(run_health_checks());
+
+        local.run_until(async move {
+            self.run(signer).await;
+        })
+        .await;
+
+        health_checks.abort();
+    }
+
+    async fn run(&self, signer: &Signer) {
+        info!("Starting the market making service...");
+        let mut next_check: DateTime<Utc> = Utc::now();
+        let mut jump = false;
+
+        // Use watch channel to signal stop request
+        let (tx, rx) = watch::channel(false);
+
+        // Create a task to listen for Ctrl+C in a separate async block
+        tokio::spawn(async move {
+            tokio::signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+            println!("\n\nCtrl+C pressed. Stopping the service...\n\n");
+            let _ = tx.send(true); // Set the stop flag
+        });
+
+        loop {
+            // Check for stop request
+            if *rx.borrow() {
+                println!("\n\n<<< Stopping the service gracefully >>>\n");
+                break;
+            }
+
+            // Sleep asynchronously
+            sleep(Duration::from_millis(POLL_INTERVAL)).await;
+
+            let now = Utc::now();
+            if now < next_check {
+                let diff = next_check - now;
+                print!("\rNext price check in: {:02}:{:02}:{:02}", diff.num_hours(), diff.num_minutes() % 60, diff.num_seconds() % 60);
+                io::stdout().flush().unwrap();
+                jump = true;
+                continue;
+            }
+            if jump {
+                println!();
+                jump = false;
+            }
+            next_check = now + ChronoDuration::seconds(self.interval_secs as i64);
+
+            let outcome = self.check_and_trade(signer).await;
+            if let Ok(Some(outcome @ TradeOutcome::Skipped { .. })) = &outcome {
+                // A skipped trade is never broadcasted, its outcome is stored apart from the ledger
+                let entry = json!({
+                    "pool_id": CONFIG.env_constants.pool_id,
+                    "token_in": CONFIG.env_constants.token_in,
+                    "token_out": CONFIG.env_constants.token_out,
+                });
+                if let Err(e) = record_unsent_outcome(signer.get_account_address(), &entry, outcome) {
+                    error!("Error recording trade outcome: {:?}", e);
+                }
+            }
+            match outcome {
+                Ok(Some(outcome)) => match outcome.next_action() {
+                    NextAction::Done => println!("Trade executed with success\n"),
+                    NextAction::Retry | NextAction::SkipWindow => println!("Trade not executed: {}", outcome),
+                    NextAction::Halt => {
+                        error!("Trade not executed, halting the market maker: {}", outcome);
+                        if let TradeOutcome::Skipped { reason: SkipReason::GuardRail { .. } } = outcome {
+                            println!("\n\n<<< Market maker halted by a guard rail, run the resume command before restarting >>>\n");
+                        } else {
+                            println!("\n\n<<< Market maker halted, fix the issue above and restart >>>\n");
+                        }
+                        break;
+                    }
+                },
+                Ok(None) => {}
+                Err(e) => error!("Error checking the market: {:?}", e),
+            }
+        }
+    }
+
+    /// Trades once if the pool price is outside the band, none when there is nothing to do
+    async fn check_and_trade(&self, signer: &Signer) -> Result<Option<TradeOutcome>> {
+        let pool_id = CONFIG.env_constants.pool_id;
+        let token_in = CONFIG.env_constants.token_in;
+        let token_out = CONFIG.env_constants.token_out;
+        let address = signer.get_account_address();
+
+        // 1. Check the guard rails on fees and realized price
+        if let Some(violation) = guard_rails::check_ledger(address, pool_id)? {
+            error!("!!! Guard rail triggered: {}", violation);
+            guard_rails::record_halt(address, &violation)?;
+            return Ok(Some(TradeOutcome::Skipped { reason: SkipReason::GuardRail { violation } }));
+        }
+
+        // 2. Compare the pool price to the band around the reference
+        let price = match fetch_validated_price(pool_id, token_in, token_out).await {
+            Ok(price) => price,
+            Err(e) => {
+                return Ok(Some(TradeOutcome::Skipped { reason: SkipReason::PriceUnavailable { error: e.to_string() } }));
+            }
+        };
+        let reference = match self.reference_price {
+            Some(reference) => reference,
+            None => fetch_reference_price(pool_id, token_in, token_out).await?,
+        };
+        let upper = reference * (1.0 + self.spread_bps as f64 / 10_000.0);
+        let lower = reference * (1.0 - self.spread_bps as f64 / 10_000.0);
+        let side = if price > upper {
+            MarketSide::Sell
+        } else if price < lower {
+            MarketSide::Buy
+        } else {
+            info!(">>> Price {} is within the band [{}, {}]", price, lower, upper);
+            return Ok(None);
+        };
+        info!(">>> Price {} is outside the band [{}, {}], {:?} {}", price, lower, upper, side, token_in);
+
+        // 3. Bound the trade by the daily volume cap and the inventory bounds
+        let mut amount = self.trade_amount;
+        if let Some(cap) = self.daily_volume_cap {
+            let traded_today = volume_today(address)?;
+            amount = amount.min(cap.saturating_sub(traded_today));
+            if amount == 0 {
+                warn!("!!! Daily volume cap of {} reached", CoinAmount { coin: token_in, amount: cap });
+                return Ok(None);
+            }
+        }
+
+        let balances = fetch_balances(address, None).await.map_err(|e| anyhow::anyhow!("Error fetching account balances: {}", e))?;
+        let balance_in = available_balance(&balances, token_in);
+        let balance_out = available_balance(&balances, token_out);
+        amount = match side {
+            // spends token_in, receives token_out
+            MarketSide::Sell => amount
+                .min(balance_in.saturating_sub(self.inventory_in.min.unwrap_or(0)))
+                .min(self.inventory_out.max.map_or(u64::MAX, |max| (max.saturating_sub(balance_out) as f64 / price) as u64)),
+            // spends token_out, receives token_in
+            MarketSide::Buy => amount
+                .min((balance_out.saturating_sub(self.inventory_out.min.unwrap_or(0)) as f64 / price) as u64)
+                .min(self.inventory_in.max.map_or(u64::MAX, |max| max.saturating_sub(balance_in))),
+        };
+        if amount == 0 {
+            warn!("!!! Inventory bounds reached, no {:?} trade possible", side);
+            return Ok(None);
+        }
+
+        // 4. Trade back towards the band, never past its edge
+        let order = match side {
+            MarketSide::Sell => SwapOrder {
+                pool_id,
+                coin_in: token_in,
+                coin_out: token_out,
+                amount,
+                swap_type: "amount_in",
+                min_price: upper,
+                max_slippage_bps: self.max_slippage_bps,
+                ladder_tier: None,
+                market_side: Some(side),
+                rebalance: false,
+                action: None,
+            },
+            MarketSide::Buy => SwapOrder {
+                pool_id,
+                coin_in: token_out,
+                coin_out: token_in,
+                amount,
+                swap_type: "amount_out",
+                // price of token_out in token_in
+                min_price: 1.0 / lower,
+                max_slippage_bps: self.max_slippage_bps,
+                ladder_tier: None,
+                market_side: Some(side),
+                rebalance: false,
+                action: None,
+            },
+        };
+        osmosis_pool_service::perform_swap(signer, &order).await.map(Some)
+    }
+}
+
+// Amount of token_in bought and sold by market making since midnight UTC
+fn volume_today(account_id: &str) -> Result<u64> {
+    let midnight = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+    let transactions = account_transactions(account_id).map_err(|e| anyhow::anyhow!("Failed to read transactions: {}", e))?;
+
+    Ok(transactions
+        .iter()
+        .filter(|tx| !tx["market_side"].is_null() && tx["tx_status"] == "executed" && tx["status_code"].as_u64() == Some(0))
+        .filter(|tx| tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()).is_some_and(|t| t >= midnight))
+        .filter_map(|tx| tx["amount"].as_u64())
+        .sum())
+}
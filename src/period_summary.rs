@@ -0,0 +1,187 @@
+use std::fmt::Write;
+use chrono::{DateTime, Datelike, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::config::CONFIG;
+use crate::chains::osmosis::osmosis_transaction::all_transactions;
+use crate::utils::format_token_amount_with_denom;
+
+/// Length of the periods swaps are grouped by, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Period {
+    Day,
+    /// ISO weeks, from Monday
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Label of the period a time falls in, e.g. `2025-01-31`, `2025-W05` or `2025-01`
+    pub fn label(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Period::Day => time.format("%Y-%m-%d").to_string(),
+            Period::Week => {
+                let week = time.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Month => time.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Swaps of a pool over a period, across accounts. Amounts are formatted with their token.
+#[derive(Debug, Serialize)]
+pub struct PeriodSummary {
+    pub period: String,
+    pub pool_id: u64,
+    pub token_in: String,
+    pub token_out: String,
+    /// swaps broadcasted in the period
+    pub tx_count: u64,
+    /// swaps executed with code 0
+    pub fills: u64,
+    /// fills over swaps, in percent
+    pub success_rate: f64,
+    pub tokens_in: String,
+    pub tokens_out: String,
+    pub average_price: String,
+    /// fees of every swap included in a block, in the gas token
+    pub fees_paid: String,
+}
+
+// Running totals of a period, before formatting
+struct Totals {
+    period: String,
+    pool_id: u64,
+    token_in: String,
+    token_out: String,
+    tx_count: u64,
+    fills: u64,
+    tokens_in: u64,
+    tokens_out: u64,
+    fees_paid: u64,
+}
+
+/// Swaps of the ledger grouped by period and pool, oldest period first.
+/// Only swaps broadcasted from `from` and before `to` (unix seconds) are counted.
+pub fn summarize_by_period(period: Period, from: Option<u64>, to: Option<u64>) -> Result<Vec<PeriodSummary>, Box<dyn std::error::Error>> {
+    let mut totals: Vec<Totals> = Vec::new();
+
+    for (_, records) in all_transactions()? {
+        for tx in records.as_array().into_iter().flatten() {
+            // Liquidity and transfer actions are not swaps
+            if !tx["action"].is_null() {
+                continue;
+            }
+            let Some(timestamp) = tx["timestamp"].as_str().and_then(|t| t.parse::<u64>().ok()) else {
+                continue;
+            };
+            if from.is_some_and(|from| timestamp < from) || to.is_some_and(|to| timestamp >= to) {
+                continue;
+            }
+            let Some(time) = DateTime::from_timestamp(timestamp as i64, 0) else {
+                continue;
+            };
+
+            let label = period.label(time);
+            let pool_id = tx["pool_id"].as_u64().unwrap_or(0);
+            let token_in = tx["token_in"].as_str().unwrap_or("unknown");
+            let token_out = tx["token_out"].as_str().unwrap_or("unknown");
+            let index = match totals.iter().position(|t| t.period == label && t.pool_id == pool_id && t.token_in == token_in && t.token_out == token_out) {
+                Some(index) => index,
+                None => {
+                    totals.push(Totals {
+                        period: label,
+                        pool_id,
+                        token_in: token_in.to_string(),
+                        token_out: token_out.to_string(),
+                        tx_count: 0,
+                        fills: 0,
+                        tokens_in: 0,
+                        tokens_out: 0,
+                        fees_paid: 0,
+                    });
+                    totals.len() - 1
+                }
+            };
+            let total = &mut totals[index];
+
+            total.tx_count += 1;
+            if tx["tx_status"] == "executed" {
+                total.fees_paid += tx["fee_paid"].as_u64().unwrap_or(0);
+                if tx["status_code"].as_u64() == Some(0) {
+                    total.fills += 1;
+                    total.tokens_in += tx["tokens_in"].as_u64().unwrap_or(0);
+                    total.tokens_out += tx["tokens_out"].as_u64().unwrap_or(0);
+                }
+            }
+        }
+    }
+
+    // Labels sort in time order
+    totals.sort_by(|a, b| a.period.cmp(&b.period).then(a.pool_id.cmp(&b.pool_id)));
+
+    let gas_token = CONFIG.gas_config.token.to_string();
+    Ok(totals
+        .into_iter()
+        .map(|total| {
+            let average_price = if total.tokens_in > 0 { total.tokens_out as f64 / total.tokens_in as f64 } else { 0.0 };
+            PeriodSummary {
+                period: total.period,
+                pool_id: total.pool_id,
+                success_rate: total.fills as f64 * 100.0 / total.tx_count as f64,
+                tx_count: total.tx_count,
+                fills: total.fills,
+                tokens_in: format_token_amount_with_denom(total.tokens_in, &total.token_in),
+                tokens_out: format_token_amount_with_denom(total.tokens_out, &total.token_out),
+                average_price: format_token_amount_with_denom((average_price * 1_000_000f64) as u64, &total.token_out),
+                fees_paid: format_token_amount_with_denom(total.fees_paid, &gas_token),
+                token_in: total.token_in,
+                token_out: total.token_out,
+            }
+        })
+        .collect())
+}
+
+/// Period summaries as an aligned text table
+pub fn format_table(summaries: &[PeriodSummary]) -> String {
+    let header = ["Period", "Pool", "Swaps", "Fills", "Success", "Tokens In", "Tokens Out", "Avg Price", "Fees"];
+    let rows: Vec<[String; 9]> = summaries
+        .iter()
+        .map(|s| [
+            s.period.clone(),
+            s.pool_id.to_string(),
+            s.tx_count.to_string(),
+            s.fills.to_string(),
+            format!("{:.1}%", s.success_rate),
+            s.tokens_in.clone(),
+            s.tokens_out.clone(),
+            s.average_price.clone(),
+            s.fees_paid.clone(),
+        ])
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    // Text columns are left aligned, numbers right aligned
+    let mut table = String::new();
+    let mut write_row = |cells: &[&str]| {
+        for (column, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            let _ = match column {
+                0 => write!(table, "{:<width$}", cell, width = width),
+                _ => write!(table, "  {:>width$}", cell, width = width),
+            };
+        }
+        table.push('\n');
+    };
+    write_row(&header);
+    for row in &rows {
+        write_row(&row.each_ref().map(String::as_str));
+    }
+    table
+}
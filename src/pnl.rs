@@ -0,0 +1,336 @@
+use std::collections::VecDeque;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::chains::coin::Coin;
+use crate::chains::osmosis::osmosis_transaction::all_transactions;
+use crate::utils::format_token_amount_with_denom;
+
+/// Which acquired lots a disposal is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostMethod {
+    /// oldest lots first
+    Fifo,
+    /// newest lots first
+    Lifo,
+    /// all lots merged at their average cost
+    Average,
+}
+
+/// A swap of the ledger that executed, amounts in microns
+#[derive(Debug, Clone)]
+pub struct ExecutedSwap {
+    pub txhash: String,
+    /// unix seconds
+    pub timestamp: u64,
+    pub token_in: Coin,
+    pub token_out: Coin,
+    pub tokens_in: u64,
+    pub tokens_out: u64,
+    /// fee valued in token_out, none when it was not valued
+    pub fee_value: Option<u64>,
+}
+
+impl ExecutedSwap {
+    /// Swap of a ledger record, none for other transactions and swaps that did not execute
+    pub fn from_record(tx: &Value) -> Option<Self> {
+        if tx["swap_type"].is_null() || tx["tx_status"] != "executed" || tx["status_code"].as_u64() != Some(0) {
+            return None;
+        }
+        Some(ExecutedSwap {
+            txhash: tx["txhash"].as_str()?.to_string(),
+            timestamp: tx["timestamp"].as_str()?.parse().ok()?,
+            token_in: tx["token_in"].as_str()?.parse().ok()?,
+            token_out: tx["token_out"].as_str()?.parse().ok()?,
+            tokens_in: tx["tokens_in"].as_u64().filter(|amount| *amount > 0)?,
+            tokens_out: tx["tokens_out"].as_u64().filter(|amount| *amount > 0)?,
+            fee_value: tx["fee_value"].as_u64(),
+        })
+    }
+
+    /// Executed swaps of ledger records, oldest first
+    pub fn from_records(records: &[Value]) -> Vec<Self> {
+        let mut swaps: Vec<Self> = records.iter().filter_map(Self::from_record).collect();
+        swaps.sort_by_key(|swap| swap.timestamp);
+        swaps
+    }
+}
+
+/// Amount of a token acquired in a single swap and its cost in the quote token
+#[derive(Debug, Clone, Copy)]
+struct Lot {
+    amount: u64,
+    cost: f64,
+}
+
+/// Lots of a token held, matched against disposals with the cost method
+#[derive(Debug)]
+struct Holdings {
+    method: CostMethod,
+    lots: VecDeque<Lot>,
+}
+
+impl Holdings {
+    fn new(method: CostMethod) -> Self {
+        Holdings { method, lots: VecDeque::new() }
+    }
+
+    fn acquire(&mut self, amount: u64, cost: f64) {
+        match (self.method, self.lots.front_mut()) {
+            (CostMethod::Average, Some(lot)) => {
+                lot.amount += amount;
+                lot.cost += cost;
+            }
+            _ => self.lots.push_back(Lot { amount, cost }),
+        }
+    }
+
+    /// Cost basis of the amount disposed and the part of it no lot covers
+    fn dispose(&mut self, mut amount: u64) -> (f64, u64) {
+        let mut cost_basis = 0.0;
+        while amount > 0 {
+            let lot = match self.method {
+                CostMethod::Lifo => self.lots.back_mut(),
+                CostMethod::Fifo | CostMethod::Average => self.lots.front_mut(),
+            };
+            let Some(lot) = lot else {
+                break;
+            };
+
+            let taken = amount.min(lot.amount);
+            let cost = lot.cost * taken as f64 / lot.amount as f64;
+            cost_basis += cost;
+            lot.cost -= cost;
+            lot.amount -= taken;
+            amount -= taken;
+            if lot.amount == 0 {
+                match self.method {
+                    CostMethod::Lifo => self.lots.pop_back(),
+                    CostMethod::Fifo | CostMethod::Average => self.lots.pop_front(),
+                };
+            }
+        }
+        (cost_basis, amount)
+    }
+
+    fn amount(&self) -> u64 {
+        self.lots.iter().map(|lot| lot.amount).sum()
+    }
+
+    fn cost(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.cost).sum()
+    }
+}
+
+/// Realized P&L of a token valued in the quote token, amounts in microns
+#[derive(Debug)]
+pub struct TokenPnl {
+    pub token: Coin,
+    pub acquired: u64,
+    pub disposed: u64,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    /// amount disposed without an acquisition in the ledger, its cost basis is 0
+    pub uncovered: u64,
+    pub holding: u64,
+    pub holding_cost: f64,
+}
+
+impl TokenPnl {
+    pub fn realized(&self) -> f64 {
+        self.proceeds - self.cost_basis
+    }
+}
+
+/// Realized P&L per token of the swaps, each swap valued in `quote` at its executed price.
+/// Acquisition costs include the fee, disposal proceeds are net of it. Swaps that
+/// do not trade the quote token cannot be valued and are counted apart.
+pub fn realized_pnl(swaps: &[ExecutedSwap], quote: Coin, method: CostMethod) -> (Vec<TokenPnl>, usize) {
+    let mut tokens: Vec<(TokenPnl, Holdings)> = Vec::new();
+    let mut unvalued = 0;
+
+    for swap in swaps {
+        let fee_value = swap.fee_value.unwrap_or(0);
+        let (token, acquisition) = if swap.token_in == quote {
+            // fee valued in token_out, converted back to the quote token at the executed price
+            let fee_cost = fee_value as f64 * swap.tokens_in as f64 / swap.tokens_out as f64;
+            (swap.token_out, Some((swap.tokens_out, swap.tokens_in as f64 + fee_cost)))
+        } else if swap.token_out == quote {
+            (swap.token_in, None)
+        } else {
+            unvalued += 1;
+            continue;
+        };
+
+        let index = match tokens.iter().position(|(pnl, _)| pnl.token == token) {
+            Some(index) => index,
+            None => {
+                let pnl = TokenPnl { token, acquired: 0, disposed: 0, proceeds: 0.0, cost_basis: 0.0, uncovered: 0, holding: 0, holding_cost: 0.0 };
+                tokens.push((pnl, Holdings::new(method)));
+                tokens.len() - 1
+            }
+        };
+        let (pnl, holdings) = &mut tokens[index];
+
+        match acquisition {
+            Some((amount, cost)) => {
+                holdings.acquire(amount, cost);
+                pnl.acquired += amount;
+            }
+            None => {
+                let (cost_basis, uncovered) = holdings.dispose(swap.tokens_in);
+                pnl.disposed += swap.tokens_in;
+                pnl.proceeds += swap.tokens_out.saturating_sub(fee_value) as f64;
+                pnl.cost_basis += cost_basis;
+                pnl.uncovered += uncovered;
+            }
+        }
+    }
+
+    let pnls = tokens
+        .into_iter()
+        .map(|(mut pnl, holdings)| {
+            pnl.holding = holdings.amount();
+            pnl.holding_cost = holdings.cost();
+            pnl
+        })
+        .collect();
+    (pnls, unvalued)
+}
+
+/// Formats an amount in microns that can be negative
+pub fn format_signed_amount(amount_microns: f64, denomination: &str) -> String {
+    let formatted = format_token_amount_with_denom(amount_microns.abs().round() as u64, denomination);
+    if amount_microns <= -0.5 {
+        formatted.replacen(' ', " -", 1)
+    } else {
+        formatted
+    }
+}
+
+/// P&L of every account of the ledger, as printed by the `pnl` command
+pub fn pnl_report(quote: Coin, method: CostMethod) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut report = serde_json::Map::new();
+    let quote_name = quote.to_string();
+
+    for (account_id, records) in all_transactions()? {
+        let records = records.as_array().cloned().unwrap_or_default();
+        let (pnls, unvalued) = realized_pnl(&ExecutedSwap::from_records(&records), quote, method);
+
+        let mut tokens = serde_json::Map::new();
+        for pnl in pnls {
+            let token = pnl.token.to_string();
+            let average_cost = if pnl.holding > 0 { pnl.holding_cost / pnl.holding as f64 } else { 0.0 };
+            tokens.insert(token.clone(), json!({
+                "acquired": format_token_amount_with_denom(pnl.acquired, &token),
+                "disposed": format_token_amount_with_denom(pnl.disposed, &token),
+                "proceeds": format_signed_amount(pnl.proceeds, &quote_name),
+                "cost_basis": format_signed_amount(pnl.cost_basis, &quote_name),
+                "realized_pnl": format_signed_amount(pnl.realized(), &quote_name),
+                "uncovered": format_token_amount_with_denom(pnl.uncovered, &token),
+                "holding": format_token_amount_with_denom(pnl.holding, &token),
+                "holding_cost": format_signed_amount(pnl.holding_cost, &quote_name),
+                "average_cost": format_signed_amount(average_cost * 1_000_000f64, &quote_name),
+            }));
+        }
+
+        report.insert(account_id, json!({
+            "method": method,
+            "quote": quote,
+            "unvalued_swaps": unvalued,
+            "tokens": tokens,
+        }));
+    }
+
+    Ok(Value::Object(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swap(timestamp: u64, token_in: Coin, token_out: Coin, tokens_in: u64, tokens_out: u64, fee_value: Option<u64>) -> ExecutedSwap {
+        ExecutedSwap { txhash: format!("TX{}", timestamp), timestamp, token_in, token_out, tokens_in, tokens_out, fee_value }
+    }
+
+    // Buys 50 OSMO at 2 USDC and 100 OSMO at 3 USDC, then sells 75 OSMO for 300 USDC,
+    // the sale takes a whole lot and part of the other
+    fn buys_then_partial_sale() -> Vec<ExecutedSwap> {
+        vec![
+            swap(1, Coin::USDC, Coin::OSMO, 100, 50, None),
+            swap(2, Coin::USDC, Coin::OSMO, 300, 100, None),
+            swap(3, Coin::OSMO, Coin::USDC, 75, 300, None),
+        ]
+    }
+
+    fn osmo_pnl(swaps: &[ExecutedSwap], method: CostMethod) -> TokenPnl {
+        let (mut pnls, unvalued) = realized_pnl(swaps, Coin::USDC, method);
+        assert_eq!(unvalued, 0);
+        assert_eq!(pnls.len(), 1);
+        pnls.remove(0)
+    }
+
+    #[test]
+    fn fifo_matches_the_oldest_lots_first() {
+        // 50 OSMO of the first lot for 100, 25 of the second for 75
+        let pnl = osmo_pnl(&buys_then_partial_sale(), CostMethod::Fifo);
+        assert_eq!((pnl.acquired, pnl.disposed, pnl.uncovered), (150, 75, 0));
+        assert_eq!(pnl.proceeds, 300.0);
+        assert_eq!(pnl.cost_basis, 175.0);
+        assert_eq!(pnl.realized(), 125.0);
+        assert_eq!((pnl.holding, pnl.holding_cost), (75, 225.0));
+    }
+
+    #[test]
+    fn lifo_matches_the_newest_lots_first() {
+        // 75 OSMO of the second lot for 225
+        let pnl = osmo_pnl(&buys_then_partial_sale(), CostMethod::Lifo);
+        assert_eq!(pnl.cost_basis, 225.0);
+        assert_eq!(pnl.realized(), 75.0);
+        assert_eq!((pnl.holding, pnl.holding_cost), (75, 175.0));
+    }
+
+    #[test]
+    fn average_matches_the_merged_lots() {
+        // 150 OSMO for 400, 75 of them for 200
+        let pnl = osmo_pnl(&buys_then_partial_sale(), CostMethod::Average);
+        assert_eq!(pnl.cost_basis, 200.0);
+        assert_eq!(pnl.realized(), 100.0);
+        assert_eq!((pnl.holding, pnl.holding_cost), (75, 200.0));
+    }
+
+    #[test]
+    fn disposal_larger_than_the_lots_held_is_uncovered() {
+        // After the first sale 75 OSMO are left for 225, the second sale of 100 leaves 25 uncovered
+        let mut swaps = buys_then_partial_sale();
+        swaps.push(swap(4, Coin::OSMO, Coin::USDC, 100, 400, None));
+        let pnl = osmo_pnl(&swaps, CostMethod::Fifo);
+        assert_eq!((pnl.disposed, pnl.uncovered), (175, 25));
+        assert_eq!(pnl.proceeds, 700.0);
+        assert_eq!(pnl.cost_basis, 400.0);
+        assert_eq!(pnl.realized(), 300.0);
+        assert_eq!((pnl.holding, pnl.holding_cost), (0, 0.0));
+    }
+
+    #[test]
+    fn fees_raise_the_cost_and_lower_the_proceeds() {
+        // The buy fee of 5 OSMO costs 10 USDC at the executed price, the sale fee is 4 USDC
+        let swaps = vec![
+            swap(1, Coin::USDC, Coin::OSMO, 100, 50, Some(5)),
+            swap(2, Coin::OSMO, Coin::USDC, 50, 200, Some(4)),
+        ];
+        let pnl = osmo_pnl(&swaps, CostMethod::Fifo);
+        assert_eq!(pnl.cost_basis, 110.0);
+        assert_eq!(pnl.proceeds, 196.0);
+        assert_eq!(pnl.realized(), 86.0);
+    }
+
+    #[test]
+    fn swaps_without_the_quote_token_are_unvalued() {
+        let swaps = vec![swap(1, Coin::OSMO, Coin::TOSMO, 10, 10, None)];
+        let (pnls, unvalued) = realized_pnl(&swaps, Coin::USDC, CostMethod::Fifo);
+        assert!(pnls.is_empty());
+        assert_eq!(unvalued, 1);
+    }
+}
@@ -1,29 +1,53 @@
 use tokio::time::{sleep, Duration};
-use chrono::{DateTime, Utc, Duration as ChronoDuration};
+use chrono::{DateTime, NaiveTime, Utc, Duration as ChronoDuration};
 use rand::Rng;
 use crate::chains::osmosis::osmosis_key_service::Signer;
 use crate::trade_service::TradeTask;
+use crate::streamer::{CatchUpPolicy, Streamer};
+use crate::ladder::LadderTier;
+use crate::campaign::Campaign;
+use crate::schedule::Schedule;
+use crate::rebalance::compute_drift;
+use crate::trade_outcome::{FailureReason, NextAction, SkipReason, TradeOutcome};
 use crate::config::CONFIG;
+use crate::chains::coin::CoinAmount;
 use std::io::{self, Write};
 use tokio::sync::watch;
 use log::{info, warn, error};
 
 const POLL_INTERVAL: u64 = 1000; // in milliseconds
 
-pub async fn start_polling(
-    signer: &Signer,
-    daily_amount: u64,
-    swap_type: &'static str,
-    streams_per_day: u64,
-    min_price: f64,
-) {
+/// Progress of a tier within the current window
+struct TierState {
+    tier: LadderTier,
+    /// amount still to be traded by this tier in the current window
+    pending: u64,
+    /// amount traded by this tier since the start of the day
+    filled_today: u64,
+    /// amount assigned to the windows of the day so far
+    allocated_today: u64,
+    /// no more attempts in this window
+    done: bool,
+    last_outcome: Option<TradeOutcome>,
+}
+
+pub async fn start_polling(signer: &Signer, streamer: &Streamer) {
     info!("Starting the polling service...");
     // Initializations
+    let streams_per_day = streamer.daily_streams;
     let mut end_window_time: DateTime<Utc> = Utc::now();
     let mut next_trade: DateTime<Utc> = Utc::now();
     let mut trade_executed = true;
-    let trade_amount: u64 = daily_amount / streams_per_day;
+    let mut first_window = true;
+    // start of the current day, windows are aligned on it
+    let mut current_day: Option<DateTime<Utc>> = None;
+    let mut tier_states: Vec<TierState> = streamer.tiers
+        .iter()
+        .map(|tier| TierState { tier: *tier, pending: 0, filled_today: 0, allocated_today: 0, done: true, last_outcome: None })
+        .collect();
     let mut jump = false;
+    // campaign progress, updated after each executed trade
+    let mut campaign = streamer.campaign.clone();
 
     // Use watch channel to signal stop request
     let (tx, rx) = watch::channel(false);
@@ -35,7 +59,7 @@ pub async fn start_polling(
         let _ = tx.send(true); // Set the stop flag
     });
     
-    loop {
+    'polling: loop {
         // Check for stop request
         if *rx.borrow() {
             println!("\n\n<<< Stopping the service gracefully >>>\n");
@@ -45,33 +69,114 @@ pub async fn start_polling(
         // Sleep asynchronously
         sleep(Duration::from_millis(POLL_INTERVAL)).await;
 
-        // 1. Check if we need a new trade window
+        // 0. Check the campaign dates and budget
         let now = Utc::now();
-        if end_window_time < now {
-            if !trade_executed {
-                warn!("Trade not executed in the last window. Skipping the next window.");
+        if let Some(campaign) = &campaign {
+            if let Some(reason) = campaign.finish_reason(now) {
+                if jump {
+                    println!();
+                }
+                info!("Campaign is over: {} ({})", reason, campaign);
+                println!("\n\n<<< Campaign completed >>>\n");
+                break;
+            }
+            if campaign.start_at > now {
+                let diff = campaign.start_at - now;
+                print!("\rCampaign starts in: {:02}:{:02}:{:02}", diff.num_hours(), diff.num_minutes() % 60, diff.num_seconds() % 60);
+                io::stdout().flush().unwrap();
+                jump = true;
+                continue;
             }
+        }
 
+        // 1. Check if we need a new trade window
+        if end_window_time < now {
             if jump { 
-                println!("");
+                println!();
                 jump = false;
             }
 
-            trade_executed = false;
+            // 1.1. Rebalancing stops once the portfolio is within the tolerance
+            let mut drift = None;
+            if let Some(target) = &streamer.rebalance {
+                match compute_drift(signer.get_account_address(), target).await {
+                    Ok(None) => {
+                        info!("Portfolio is within {}% of its target", target.tolerance_pct);
+                        println!("\n\n<<< Rebalance completed >>>\n");
+                        break;
+                    }
+                    Ok(Some(current)) if current.sell != streamer.token_in => {
+                        info!("Portfolio overshot its target ({}), run the rebalance again", current);
+                        println!("\n\n<<< Rebalance completed >>>\n");
+                        break;
+                    }
+                    Ok(Some(current)) => {
+                        info!("Portfolio drift: {}", current);
+                        drift = Some(current);
+                    }
+                    Err(e) => error!("Error computing the portfolio drift: {:?}", e),
+                }
+            }
+
+            // 1.2. Find the wall-clock window containing now
+            let window = current_window(now, streamer.daily_reset, streams_per_day);
+            let window_in_day = window.index;
+            let new_day = current_day != Some(window.day_start);
+            current_day = Some(window.day_start);
+
+            for (state, tier) in tier_states.iter_mut().zip(&streamer.tiers) {
+                if !first_window && state.pending > 0 {
+                    match &state.last_outcome {
+                        Some(outcome) => warn!("Trade not executed in the last window ({}): {}", state.tier, outcome),
+                        None => warn!("Trade not executed in the last window ({})", state.tier),
+                    }
+                    match streamer.catch_up {
+                        CatchUpPolicy::None => info!("Dropping {} missed in the last window ({})", state.pending, state.tier),
+                        CatchUpPolicy::Spread => info!("Spreading {} over the remaining windows of the day ({})", state.pending, state.tier),
+                        CatchUpPolicy::NextWindow => info!("Carrying {} forward to the next window ({})", state.pending, state.tier),
+                    }
+                }
+
+                if new_day {
+                    // Campaigns derive the daily amount from the remaining budget and days
+                    if let Some(campaign) = &campaign {
+                        state.tier.daily_amount = campaign.daily_rate(tier.daily_amount, now);
+                        info!("Campaign daily amount: {} ({})", state.tier.daily_amount, campaign);
+                    }
 
-            // 1.1. Calculate the end time of the next window
-            let window_duration = ChronoDuration::hours(24) / streams_per_day as i32;
-            end_window_time = now + window_duration;
+                    // A new day starts with nothing filled, the windows that passed
+                    // before the stream started are not owed
+                    state.filled_today = if first_window { state.tier.daily_amount * window_in_day / streams_per_day } else { 0 };
+                    state.allocated_today = state.filled_today;
+                }
+                state.pending = window_target(state, streamer, window_in_day);
+                if let Some(drift) = &drift {
+                    state.pending = state.pending.min(drift.amount);
+                }
+                state.done = state.pending == 0;
+                state.last_outcome = None;
+            }
+            first_window = false;
+            end_window_time = window.end;
 
-            // 1.2. Generate a random time between now and the end of the window
-            next_trade = generate_next_trade_time(now, end_window_time);
+            // 1.3. Generate a random time between now and the end of the window, within trading hours
+            match generate_next_trade_time(now.max(window.start), end_window_time, streamer.schedule.as_ref()) {
+                Some(time) => {
+                    next_trade = time;
+                    trade_executed = false;
+                }
+                None => {
+                    info!("No trading hours left in this window");
+                    trade_executed = true;
+                }
+            }
         }
 
         // 2. Check if we have already traded in this window
         if trade_executed {
             let diff = end_window_time - now;
             let remaining = format!("{:02}:{:02}:{:02}", diff.num_hours(), diff.num_minutes() % 60, diff.num_seconds() % 60);
-            print!("\rNext window starts in: {}", remaining);
+            print!("\rNext window starts in: {} | {}", remaining, remaining_to_target(&tier_states, streamer, campaign.as_ref()));
             io::stdout().flush().unwrap();
             jump = true;
             continue;
@@ -79,59 +184,342 @@ pub async fn start_polling(
 
         // 3. Check if it's time to trade
         if next_trade < now {
+            // Retries wait for the trading hours to open again
+            if streamer.schedule.as_ref().is_some_and(|schedule| !schedule.is_open(now)) {
+                print!("\rOutside trading hours | {}", remaining_to_target(&tier_states, streamer, campaign.as_ref()));
+                io::stdout().flush().unwrap();
+                jump = true;
+                continue;
+            }
             if jump { 
-                println!("");
+                println!();
                 jump = false;
             };
-            println!("Try to execute trade...");
-            
-            // Create a new trade task
-            let task = TradeTask::new(
-                CONFIG.env_constants.pool_id,
-                CONFIG.env_constants.token_in.clone(),
-                CONFIG.env_constants.token_out.clone(),
-                trade_amount,
-                swap_type,
-                min_price,
-            );
-
-            // Execute the task directly
-            let ret = task.execute(signer).await;
-
-            // print response
-            match ret {
-                Ok(boo) => {
-                    trade_executed = boo;
-                    if boo {
-                        println!("Trade executed with success\n");
-                    } else {
-                        println!("Trade not executed");
+            for state in tier_states.iter_mut().filter(|state| !state.done) {
+                println!("Try to execute trade ({})...", state.tier);
+
+                let amount = trade_amount(state.pending, streamer, campaign.as_ref());
+
+                // Create a new trade task
+                let mut task = TradeTask::new(
+                    CONFIG.env_constants.pool_id,
+                    streamer.token_in,
+                    streamer.token_out,
+                    amount,
+                    streamer.swap_type,
+                    state.tier.min_price,
+                    streamer.max_slippage_bps,
+                );
+                if streamer.ladder {
+                    task = task.with_ladder_tier(state.tier.min_price);
+                }
+                if streamer.rebalance.is_some() {
+                    task = task.with_rebalance();
+                }
+
+                // Execute the task directly
+                let ret = task.execute(signer).await;
+
+                // print response and decide what to do next
+                match ret {
+                    Ok(outcome) => {
+                        match outcome.next_action() {
+                            NextAction::Done => {
+                                println!("Trade executed with success\n");
+                                state.pending -= amount;
+                                state.filled_today += amount;
+                                if let Some(campaign) = campaign.as_mut() {
+                                    campaign.filled += amount;
+                                }
+                                state.done = true;
+
+                                // Move the proceeds out of the hot account, a failed sweep is retried after the next trade
+                                if let Some(sweep) = &streamer.sweep {
+                                    match sweep.sweep(signer, streamer.token_out).await {
+                                        Ok(Some(outcome)) if outcome.next_action() == NextAction::Done => println!("Proceeds swept with success\n"),
+                                        Ok(Some(outcome)) => error!("Proceeds not swept: {}", outcome),
+                                        Ok(None) => {}
+                                        Err(e) => error!("Error sweeping proceeds: {:?}", e),
+                                    }
+                                }
+                            },
+                            NextAction::Retry => {
+                                println!("Trade not executed: {}", outcome);
+                            },
+                            NextAction::SkipWindow => {
+                                println!("Trade not executed, skipping this window: {}", outcome);
+                                // A timed out tx may still land, its amount is counted as filled
+                                // so the next windows do not trade it a second time
+                                if let TradeOutcome::Failed { reason: FailureReason::Timeout, .. } = outcome {
+                                    state.pending -= amount;
+                                    state.filled_today += amount;
+                                    if let Some(campaign) = campaign.as_mut() {
+                                        campaign.filled += amount;
+                                    }
+                                }
+                                state.done = true;
+                            },
+                            NextAction::Halt => {
+                                error!("Trade not executed, halting the stream: {}", outcome);
+                                if let TradeOutcome::Skipped { reason: SkipReason::GuardRail { .. } } = outcome {
+                                    println!("\n\n<<< Stream halted by a guard rail, run the resume command before restarting >>>\n");
+                                } else {
+                                    println!("\n\n<<< Stream halted, fix the issue above and restart >>>\n");
+                                }
+                                break 'polling;
+                            },
+                        }
+                        state.last_outcome = Some(outcome);
+                    },
+                    Err(e) => {
+                        error!("Error executing trade: {:?}", e);
                     }
-                },
-                Err(e) => {
-                    error!("Error executing trade: {:?}", e);
                 }
             }
+            trade_executed = tier_states.iter().all(|state| state.done);
             
             continue;
         }
 
         let diff = next_trade - now;
         let remaining = format!("{:02}:{:02}:{:02}", diff.num_hours(), diff.num_minutes() % 60, diff.num_seconds() % 60);
-        print!("\rNext trade starts in: {}", remaining);
+        print!("\rNext trade starts in: {} | {}", remaining, remaining_to_target(&tier_states, streamer, campaign.as_ref()));
         jump = true;
         io::stdout().flush().unwrap();
     }
 }
 
-fn generate_next_trade_time(now: DateTime<Utc>, end_window_time: DateTime<Utc>) -> DateTime<Utc> {
+// Amount a tier should trade in the window that is starting, `pending` still
+// holds what was missed in the previous window. What is carried forward never
+// exceeds the part of the daily amount that is not filled yet.
+fn window_target(state: &mut TierState, streamer: &Streamer, window_in_day: u64) -> u64 {
+    let windows_left = streamer.daily_streams - window_in_day;
+    let jitter_pct = streamer.size_jitter_pct;
+
+    // The share of the day that is not assigned to a window yet, randomized
+    // except in the last window which takes the rest so the day's total holds
+    let unallocated = state.tier.daily_amount.saturating_sub(state.allocated_today);
+    let window_amount = if windows_left == 1 { unallocated } else { jitter(unallocated / windows_left, jitter_pct).min(unallocated) };
+    state.allocated_today += window_amount;
+
+    match streamer.catch_up {
+        CatchUpPolicy::None => window_amount,
+        CatchUpPolicy::NextWindow => (state.pending + window_amount).min(state.tier.daily_amount.saturating_sub(state.filled_today)),
+        CatchUpPolicy::Spread => {
+            let unfilled = state.tier.daily_amount.saturating_sub(state.filled_today);
+            if windows_left == 1 { unfilled } else { jitter(unfilled / windows_left, jitter_pct).min(unfilled) }
+        }
+    }
+}
+
+// Catch-up trades are capped, the rest stays pending
+fn trade_amount(pending: u64, streamer: &Streamer, campaign: Option<&Campaign>) -> u64 {
+    let amount = streamer.max_trade_amount.map_or(pending, |cap| pending.min(cap));
+    campaign.map_or(amount, |campaign| amount.min(campaign.remaining()))
+}
+
+// Random amount within +/- `jitter_pct` percent of the given amount
+fn jitter(amount: u64, jitter_pct: f64) -> u64 {
+    if jitter_pct <= 0.0 {
+        return amount;
+    }
+    let factor = 1.0 + rand::thread_rng().gen_range(-jitter_pct..=jitter_pct) / 100.0;
+    (amount as f64 * factor) as u64
+}
+
+// Amount left to reach the daily target of all tiers, for the countdown
+fn remaining_to_target(tier_states: &[TierState], streamer: &Streamer, campaign: Option<&Campaign>) -> String {
+    let coin = if streamer.swap_type == "amount_in" { streamer.token_in } else { streamer.token_out };
+    let remaining: u64 = tier_states
+        .iter()
+        .map(|state| state.tier.daily_amount.saturating_sub(state.filled_today))
+        .sum();
+    match campaign {
+        Some(campaign) => format!("remaining today: {} | campaign: {}", CoinAmount { coin, amount: remaining }, CoinAmount { coin, amount: campaign.remaining() }),
+        None => format!("remaining today: {}", CoinAmount { coin, amount: remaining }),
+    }
+}
+
+/// A trade window, aligned on the daily reset time
+struct Window {
+    day_start: DateTime<Utc>,
+    /// position of the window in the day, from 0
+    index: u64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+// Days start at the reset time and are divided into `streams_per_day` windows of equal length
+fn current_window(now: DateTime<Utc>, daily_reset: NaiveTime, streams_per_day: u64) -> Window {
+    let mut day_start = now.date_naive().and_time(daily_reset).and_utc();
+    if day_start > now {
+        day_start -= ChronoDuration::days(1);
+    }
+
+    let window_secs = (ChronoDuration::days(1).num_seconds() / streams_per_day as i64).max(1);
+    let index = ((now - day_start).num_seconds() / window_secs).min(streams_per_day as i64 - 1);
+    let start = day_start + ChronoDuration::seconds(window_secs * index);
+    // The last window absorbs the rounding so the day ends at the next reset
+    let end = if index == streams_per_day as i64 - 1 {
+        day_start + ChronoDuration::days(1)
+    } else {
+        start + ChronoDuration::seconds(window_secs)
+    };
+
+    Window { day_start, index: index as u64, start, end }
+}
+
+// Returns none when the schedule is closed for the whole window
+fn generate_next_trade_time(now: DateTime<Utc>, end_window_time: DateTime<Utc>, schedule: Option<&Schedule>) -> Option<DateTime<Utc>> {
     let now_timestamp = now.timestamp();
     let end_timestamp = end_window_time.timestamp();
     let mut rng = rand::thread_rng();
-    let random_timestamp = rng.gen_range(now_timestamp..end_timestamp);
 
-    // Use DateTime::from_timestamp to create the DateTime directly
-    DateTime::<Utc>::from_timestamp(random_timestamp, 0).unwrap()
+    let Some(schedule) = schedule else {
+        let random_timestamp = rng.gen_range(now_timestamp..end_timestamp);
+
+        // Use DateTime::from_timestamp to create the DateTime directly
+        return DateTime::<Utc>::from_timestamp(random_timestamp, 0);
+    };
+
+    // Pick a random open minute of the window
+    let open_minutes: Vec<i64> = (now_timestamp..end_timestamp)
+        .step_by(60)
+        .filter(|&timestamp| DateTime::<Utc>::from_timestamp(timestamp, 0).is_some_and(|time| schedule.is_open(time)))
+        .collect();
+    let minute = *open_minutes.get(rng.gen_range(0..open_minutes.len().max(1)))?;
+    let random_timestamp = rng.gen_range(minute..(minute + 60).min(end_timestamp));
+
+    DateTime::<Utc>::from_timestamp(random_timestamp, 0)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tier(daily_amount: u64, min_price: f64) -> LadderTier {
+        LadderTier { daily_amount, min_price }
+    }
+
+    fn tier_state(tier: LadderTier) -> TierState {
+        TierState { tier, pending: 0, filled_today: 0, allocated_today: 0, done: true, last_outcome: None }
+    }
+
+    // A day of 4 windows trading 100 without size jitter
+    fn streamer(catch_up: CatchUpPolicy, max_trade_amount: Option<u64>) -> Streamer {
+        Streamer::new(vec![tier(100, 1.0)], false, "amount_in", 4, None).with_catch_up(catch_up, max_trade_amount)
+    }
+
+    // Starts the window and trades what the per-trade cap allows, as the poller does on success
+    fn start_and_fill(state: &mut TierState, streamer: &Streamer, window_in_day: u64) -> u64 {
+        state.pending = window_target(state, streamer, window_in_day);
+        let amount = trade_amount(state.pending, streamer, None);
+        state.pending -= amount;
+        state.filled_today += amount;
+        amount
+    }
+
+    #[test]
+    fn ladder_carries_unfilled_tiers_to_the_next_window() {
+        let tiers = vec![tier(100, 1.0), tier(40, 2.0)];
+        let streamer = Streamer::new(tiers.clone(), true, "amount_in", 4, None);
+        let mut low = tier_state(tiers[0]);
+        let mut high = tier_state(tiers[1]);
+
+        // The price only reaches the low tier in the first window
+        assert_eq!(start_and_fill(&mut low, &streamer, 0), 25);
+        high.pending = window_target(&mut high, &streamer, 0);
+        assert_eq!(high.pending, 10);
+
+        assert_eq!(window_target(&mut low, &streamer, 1), 25);
+        assert_eq!(window_target(&mut high, &streamer, 1), 20);
+    }
 
+    #[test]
+    fn no_catch_up_drops_missed_volume() {
+        let streamer = streamer(CatchUpPolicy::None, None);
+        let mut state = tier_state(streamer.tiers[0]);
+
+        state.pending = window_target(&mut state, &streamer, 0);
+        assert_eq!(state.pending, 25);
+        assert_eq!(window_target(&mut state, &streamer, 1), 25);
+        assert_eq!(window_target(&mut state, &streamer, 2), 25);
+        assert_eq!(window_target(&mut state, &streamer, 3), 25);
+    }
+
+    #[test]
+    fn next_window_carries_what_the_cap_left_pending() {
+        let streamer = streamer(CatchUpPolicy::NextWindow, Some(30));
+        let mut state = tier_state(streamer.tiers[0]);
+
+        // Missed window
+        state.pending = window_target(&mut state, &streamer, 0);
+        assert_eq!(state.pending, 25);
+
+        // 25 carried, the trade is capped at 30 and 20 stays pending
+        assert_eq!(start_and_fill(&mut state, &streamer, 1), 30);
+        assert_eq!(state.pending, 20);
+
+        assert_eq!(start_and_fill(&mut state, &streamer, 2), 30);
+        assert_eq!(state.pending, 15);
+
+        // The carry never exceeds the unfilled part of the day
+        assert_eq!(window_target(&mut state, &streamer, 3), 40);
+    }
+
+    #[test]
+    fn spread_divides_unfilled_volume_over_the_windows_left() {
+        let streamer = streamer(CatchUpPolicy::Spread, Some(30));
+        let mut state = tier_state(streamer.tiers[0]);
+
+        // Missed window
+        state.pending = window_target(&mut state, &streamer, 0);
+        assert_eq!(state.pending, 25);
+
+        assert_eq!(start_and_fill(&mut state, &streamer, 1), 30);
+        assert_eq!(state.pending, 3);
+        assert_eq!(start_and_fill(&mut state, &streamer, 2), 30);
+        assert_eq!(state.pending, 5);
+
+        // The last window takes all that is unfilled
+        assert_eq!(window_target(&mut state, &streamer, 3), 40);
+    }
+
+    #[test]
+    fn trade_amount_is_capped_by_the_campaign_budget() {
+        let streamer = streamer(CatchUpPolicy::NextWindow, Some(30));
+        let campaign = Campaign { total_amount: 100, start_at: Utc::now(), end_at: None, filled: 90 };
+
+        assert_eq!(trade_amount(50, &streamer, None), 30);
+        assert_eq!(trade_amount(20, &streamer, None), 20);
+        assert_eq!(trade_amount(50, &streamer, Some(&campaign)), 10);
+    }
+
+    #[test]
+    fn windows_are_aligned_on_the_daily_reset() {
+        let reset = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+
+        // Before the reset, the window belongs to the previous day
+        let window = current_window(Utc.with_ymd_and_hms(2024, 1, 2, 5, 0, 0).unwrap(), reset, 4);
+        assert_eq!(window.day_start, Utc.with_ymd_and_hms(2024, 1, 1, 6, 0, 0).unwrap());
+        assert_eq!(window.index, 3);
+        assert_eq!(window.start, Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap());
+        assert_eq!(window.end, Utc.with_ymd_and_hms(2024, 1, 2, 6, 0, 0).unwrap());
+
+        let window = current_window(Utc.with_ymd_and_hms(2024, 1, 2, 7, 30, 0).unwrap(), reset, 4);
+        assert_eq!(window.day_start, Utc.with_ymd_and_hms(2024, 1, 2, 6, 0, 0).unwrap());
+        assert_eq!(window.index, 0);
+        assert_eq!(window.start, Utc.with_ymd_and_hms(2024, 1, 2, 6, 0, 0).unwrap());
+        assert_eq!(window.end, Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn last_window_ends_at_the_next_reset() {
+        // 86400 seconds do not divide in 7 windows
+        let window = current_window(Utc.with_ymd_and_hms(2024, 1, 1, 23, 59, 59).unwrap(), NaiveTime::MIN, 7);
+        assert_eq!(window.index, 6);
+        assert_eq!(window.start, Utc.with_ymd_and_hms(2024, 1, 1, 20, 34, 12).unwrap());
+        assert_eq!(window.end, Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap());
+    }
+}
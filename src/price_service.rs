@@ -0,0 +1,115 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Deserialize;
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use crate::config::{CONFIG, PriceReference};
+use crate::chains::coin::Coin;
+use crate::chains::osmosis::osmosis_pool_service::{fetch_coin_price_quotes, fetch_pool_price, fetch_twap_price};
+
+/// Price published by an external process in the configured oracle file
+#[derive(Deserialize)]
+struct OraclePrice {
+    price: f64,
+    /// unix seconds
+    updated_at: u64,
+}
+
+/// Returns the price of `token_in` in `token_out` after the swap fee, only if it is
+/// confirmed by the configured sources. Errors when there are not enough pool quotes,
+/// when the quotes diverge from each other, or when they diverge from the reference price.
+pub async fn fetch_validated_price(pool_id: u64, token_in: Coin, token_out: Coin) -> Result<f64> {
+    let config = &CONFIG.price_validation;
+
+    // Single source and no reference, nothing to cross-check
+    if config.min_sources <= 1 && config.reference == PriceReference::None {
+        let pool_price = fetch_pool_price(pool_id).await.map_err(|e| anyhow!("Failed to fetch coin price: {}", e))?;
+        return pool_price.price_of(token_in, token_out);
+    }
+
+    // 1. Pool quotes from independent providers, at least one even when min_sources is 0
+    let quotes = fetch_coin_price_quotes(pool_id).await;
+    let required_sources = config.min_sources.max(1);
+    if quotes.len() < required_sources {
+        bail!("Only {} of the required {} price sources answered", quotes.len(), required_sources);
+    }
+    let mut prices = Vec::new();
+    let mut spot_prices = Vec::new();
+    for (provider, pool_price) in &quotes {
+        let price = pool_price.price_of(token_in, token_out)?;
+        info!("Pool {} price {} from {}", pool_id, price, provider);
+        prices.push(price);
+        spot_prices.push(pool_price.spot_of(token_in, token_out)?);
+    }
+
+    prices.sort_by(|a, b| a.total_cmp(b));
+    spot_prices.sort_by(|a, b| a.total_cmp(b));
+    let lowest = prices[0];
+    let highest = prices[prices.len() - 1];
+    let spread = deviation_bps(highest, lowest);
+    if spread > config.max_deviation_bps as f64 {
+        bail!("Price sources diverge by {:.0} bps (lowest {}, highest {}), max allowed is {} bps", spread, lowest, highest, config.max_deviation_bps);
+    }
+    let price = median(&prices);
+
+    // 2. Independent reference, quoted without the swap fee like the pool spot price
+    if config.reference == PriceReference::None {
+        return Ok(price);
+    }
+    let spot_price = median(&spot_prices);
+    let reference = fetch_reference_price(pool_id, token_in, token_out).await?;
+    let reference_spread = deviation_bps(spot_price, reference);
+    if reference_spread > config.max_reference_deviation_bps as f64 {
+        bail!("Pool spot price {} diverges from reference {} by {:.0} bps, max allowed is {} bps", spot_price, reference, reference_spread, config.max_reference_deviation_bps);
+    }
+    info!("Pool spot price {} confirmed by reference {} ({:.0} bps)", spot_price, reference, reference_spread);
+
+    Ok(price)
+}
+
+/// Returns the configured reference price (TWAP or oracle file) of `token_in` in `token_out`, without swap fee
+pub async fn fetch_reference_price(pool_id: u64, token_in: Coin, token_out: Coin) -> Result<f64> {
+    match CONFIG.price_validation.reference {
+        PriceReference::None => bail!("No price reference is configured"),
+        PriceReference::Twap => fetch_twap_reference(pool_id, token_in, token_out).await,
+        PriceReference::OracleFile => read_oracle_reference(),
+    }
+}
+
+async fn fetch_twap_reference(pool_id: u64, token_in: Coin, token_out: Coin) -> Result<f64> {
+    let config = &CONFIG.price_validation;
+    let twap_url = config.twap_url.as_deref().ok_or_else(|| anyhow!("twap_url must be set to use the TWAP reference"))?;
+
+    fetch_twap_price(twap_url, pool_id, token_in, token_out, config.twap_window_secs)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch TWAP: {}", e))
+}
+
+fn read_oracle_reference() -> Result<f64> {
+    let config = &CONFIG.price_validation;
+    let path = config.oracle_file.as_ref().ok_or_else(|| anyhow!("oracle_file must be set to use the oracle reference"))?;
+
+    let oracle: OraclePrice = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let age = now.saturating_sub(oracle.updated_at);
+    if age > config.oracle_max_age_secs {
+        bail!("Oracle price is {} seconds old, max allowed is {} seconds", age, config.oracle_max_age_secs);
+    }
+
+    Ok(oracle.price)
+}
+
+// Relative distance between two prices, in basis points of the second one
+fn deviation_bps(price: f64, reference: f64) -> f64 {
+    ((price - reference) / reference).abs() * 10_000.0
+}
+
+// Expects sorted prices
+fn median(prices: &[f64]) -> f64 {
+    let middle = prices.len() / 2;
+    if prices.len().is_multiple_of(2) {
+        (prices[middle - 1] + prices[middle]) / 2.0
+    } else {
+        prices[middle]
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Result};
+use crate::config::CONFIG;
+use crate::chains::coin::{Coin, CoinAmount};
+use crate::chains::osmosis::osmosis_account_service::fetch_balances;
+use crate::chains::osmosis::osmosis_pool_service::fetch_pool_price;
+use crate::trade_service::available_balance;
+
+/// Target weights by value, formatted as `<coin>=<weight>,...` e.g. `USDC=60,WLibra=40`.
+/// Weights are relative, they are normalized to their sum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights(pub Vec<(Coin, f64)>);
+
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+
+        for weight in input.split(',') {
+            let (coin, value) = weight
+                .trim()
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid weight '{}', expected <coin>=<weight>", weight))?;
+            let coin: Coin = coin.trim().parse().map_err(|_| format!("Unknown coin in weight '{}'", weight))?;
+            let value: f64 = value.trim().parse().map_err(|e| format!("Invalid value in weight '{}': {}", weight, e))?;
+            if value < 0.0 {
+                return Err(format!("Weight '{}' must not be negative", weight));
+            }
+            weights.push((coin, value));
+        }
+
+        if weights.iter().map(|(_, value)| value).sum::<f64>() <= 0.0 {
+            return Err("Weights must not all be zero".to_string());
+        }
+
+        Ok(Weights(weights))
+    }
+}
+
+/// Portfolio the rebalance stream trades towards, over the two tokens of the configured pool
+#[derive(Debug, Clone, Copy)]
+pub struct RebalanceTarget {
+    /// share of the portfolio value held in token_in, from 0 to 1
+    pub weight_in: f64,
+    /// distance from the target weight that is accepted, in percentage points
+    pub tolerance_pct: f64,
+}
+
+impl RebalanceTarget {
+    /// Target over the configured pool, fails when the weights name other coins
+    pub fn from_weights(weights: &Weights, tolerance_pct: f64) -> Result<Self> {
+        let token_in = CONFIG.env_constants.token_in;
+        let token_out = CONFIG.env_constants.token_out;
+
+        let mut weight_in = 0.0;
+        let mut total = 0.0;
+        for (coin, value) in &weights.0 {
+            if *coin == token_in {
+                weight_in += value;
+            } else if *coin != token_out {
+                bail!("Only {} and {} can be rebalanced on pool {}", token_in, token_out, CONFIG.env_constants.pool_id);
+            }
+            total += value;
+        }
+
+        Ok(RebalanceTarget { weight_in: weight_in / total, tolerance_pct })
+    }
+}
+
+/// Distance of the portfolio from its target
+#[derive(Debug, Clone, Copy)]
+pub struct Drift {
+    /// current share of the portfolio value held in token_in, from 0 to 1
+    pub weight_in: f64,
+    /// the overweight token, to sell
+    pub sell: Coin,
+    /// amount of `sell` to swap to reach the target, in microns
+    pub amount: u64,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}% in {}, sell {}", self.weight_in * 100.0, CONFIG.env_constants.token_in, CoinAmount { coin: self.sell, amount: self.amount })
+    }
+}
+
+/// Computes the drift of the account from the target, none when within the tolerance
+pub async fn compute_drift(address: &str, target: &RebalanceTarget) -> Result<Option<Drift>> {
+    let token_in = CONFIG.env_constants.token_in;
+    let token_out = CONFIG.env_constants.token_out;
+
+    let balances = fetch_balances(address, None).await.map_err(|e| anyhow!("Error fetching account balances: {}", e))?;
+    let pool_price = fetch_pool_price(CONFIG.env_constants.pool_id).await.map_err(|e| anyhow!("Error fetching coin price: {}", e))?;
+
+    // Values in token_out at the spot price, the swap fee is a cost of trading and not of holding
+    let price = pool_price.spot_of(token_in, token_out)?;
+    let value_in = available_balance(&balances, token_in) as f64 * price;
+    let value_out = available_balance(&balances, token_out) as f64;
+    let total = value_in + value_out;
+    if total <= 0.0 {
+        bail!("The account holds neither {} nor {}", token_in, token_out);
+    }
+
+    let weight_in = value_in / total;
+    let excess = (weight_in - target.weight_in) * total;
+    if (weight_in - target.weight_in).abs() * 100.0 <= target.tolerance_pct {
+        return Ok(None);
+    }
+
+    Ok(Some(if excess > 0.0 {
+        Drift { weight_in, sell: token_in, amount: (excess / price) as u64 }
+    } else {
+        Drift { weight_in, sell: token_out, amount: -excess as u64 }
+    }))
+}
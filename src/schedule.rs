@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+/// Trading hours calendar, formatted as `[days] [hours] [time zone]`
+/// e.g. `Mon-Fri 08:00-12:00,14:00-18:00 Europe/Paris`.
+/// Days and hours default to all, the time zone defaults to UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// allowed weekdays, indexed from Monday
+    days: [bool; 7],
+    /// allowed local hours, an end before the start wraps past midnight
+    hours: Vec<(NaiveTime, NaiveTime)>,
+    time_zone: Tz,
+    spec: String,
+}
+
+impl Schedule {
+    /// Whether trading is allowed at the given time
+    pub fn is_open(&self, time: DateTime<Utc>) -> bool {
+        let local = time.with_timezone(&self.time_zone);
+        let day = local.weekday().num_days_from_monday() as usize;
+        let previous_day = (day + 6) % 7;
+        let now = local.time();
+
+        if self.hours.is_empty() {
+            return self.days[day];
+        }
+        self.hours.iter().any(|&(start, end)| {
+            if start < end {
+                self.days[day] && start <= now && now < end
+            } else {
+                // The hours after midnight belong to the previous day's range
+                (self.days[day] && now >= start) || (self.days[previous_day] && now < end)
+            }
+        })
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut days = None;
+        let mut hours = Vec::new();
+        let mut time_zone = Tz::UTC;
+
+        for token in input.split_whitespace() {
+            if let Ok(tz) = token.parse::<Tz>() {
+                time_zone = tz;
+            } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+                for range in token.split(',') {
+                    hours.push(parse_hours(range)?);
+                }
+            } else if days.is_none() {
+                days = Some(parse_days(token)?);
+            } else {
+                return Err(format!("Invalid schedule token '{}'", token));
+            }
+        }
+
+        Ok(Schedule {
+            days: days.unwrap_or([true; 7]),
+            hours,
+            time_zone,
+            spec: input.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+// `*`, `Mon-Fri` or `Mon,Wed,Fri`
+fn parse_days(token: &str) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    if token == "*" {
+        return Ok([true; 7]);
+    }
+
+    for part in token.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first = parse_weekday(first)?;
+                let last = parse_weekday(last)?;
+                // Ranges may wrap, e.g. Sat-Mon
+                let mut day = first;
+                loop {
+                    days[day] = true;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_weekday(part)?] = true,
+        }
+    }
+
+    Ok(days)
+}
+
+fn parse_weekday(input: &str) -> Result<usize, String> {
+    let weekday: Weekday = input.parse().map_err(|_| format!("Invalid weekday '{}'", input))?;
+    Ok(WEEKDAYS.iter().position(|&day| day == weekday).unwrap())
+}
+
+// `HH:MM-HH:MM`
+fn parse_hours(range: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let (start, end) = range
+        .split_once('-')
+        .ok_or_else(|| format!("Invalid hours '{}', expected HH:MM-HH:MM", range))?;
+    let start = parse_time(start)?;
+    let end = parse_time(end)?;
+    if start == end {
+        return Err(format!("Invalid hours '{}', start and end are equal", range));
+    }
+    Ok((start, end))
+}
+
+/// Parses a `HH:MM` time
+pub fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(input, "%H:%M").map_err(|_| format!("Invalid time '{}', expected HH:MM", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // 2024-01-05 is a Friday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn hours_wrapping_past_midnight_belong_to_the_starting_day() {
+        let schedule: Schedule = "Fri 22:00-02:00".parse().unwrap();
+
+        assert!(!schedule.is_open(at(5, 21, 59)));
+        assert!(schedule.is_open(at(5, 22, 0)));
+        assert!(schedule.is_open(at(6, 1, 59)));
+        assert!(!schedule.is_open(at(6, 2, 0)));
+        // Friday after midnight is part of Thursday's range
+        assert!(!schedule.is_open(at(5, 1, 0)));
+        assert!(!schedule.is_open(at(6, 23, 0)));
+    }
+
+    #[test]
+    fn hours_are_local_to_the_time_zone() {
+        let schedule: Schedule = "Mon-Fri 08:00-12:00,14:00-18:00 Europe/Paris".parse().unwrap();
+
+        // UTC+1 in winter
+        assert!(!schedule.is_open(at(5, 6, 59)));
+        assert!(schedule.is_open(at(5, 7, 0)));
+        assert!(!schedule.is_open(at(5, 12, 0)));
+        assert!(schedule.is_open(at(5, 13, 0)));
+        assert!(!schedule.is_open(at(6, 13, 0)));
+    }
+
+    #[test]
+    fn day_ranges_wrap_past_sunday() {
+        let schedule: Schedule = "Sat-Mon".parse().unwrap();
+
+        assert!(!schedule.is_open(at(5, 12, 0)));
+        assert!(schedule.is_open(at(6, 12, 0)));
+        assert!(schedule.is_open(at(7, 12, 0)));
+        assert!(schedule.is_open(at(8, 12, 0)));
+        assert!(!schedule.is_open(at(9, 12, 0)));
+        assert!("Mon-Fri 08:00 UTC".parse::<Schedule>().is_err());
+    }
+}
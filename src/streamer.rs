@@ -1,56 +1,148 @@
 use crate::poll_service;
+use crate::ladder::LadderTier;
+use crate::campaign::Campaign;
+use crate::schedule::Schedule;
+use crate::rebalance::RebalanceTarget;
+use crate::sweep::SweepPolicy;
+use crate::chains::coin::Coin;
+use crate::config::CONFIG;
+use chrono::NaiveTime;
 use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_endpoint_service::run_health_checks;
 use tokio::task::LocalSet;
+use clap::ValueEnum;
+
+/// How volume missed in a window is made up for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CatchUpPolicy {
+    /// missed volume is dropped
+    None,
+    /// missed volume is spread over the remaining windows of the day
+    Spread,
+    /// missed volume is added to the next window
+    NextWindow,
+}
 
 /// creates a Streamer struct, which will enclose the services
 /// needed to run the trade stream
 // main app logic, and entry point for external libraries
 pub struct Streamer {
-    /// amount goal per day
-    pub daily_amount: u64,
+    /// amount goal per day and min price of each tier,
+    /// a regular stream has a single tier
+    pub tiers: Vec<LadderTier>,
+
+    /// ladder mode, fills are recorded per tier
+    pub ladder: bool,
+
+    /// how volume missed in a window is made up for
+    pub catch_up: CatchUpPolicy,
+
+    /// max amount of a single trade, in microns
+    pub max_trade_amount: Option<u64>,
+
+    /// max random deviation of each window amount, in percent, the day's total is kept
+    pub size_jitter_pct: f64,
 
     pub swap_type: &'static str, // "amount_out" ou "amount_in"
 
+    /// swap direction, the configured token_in and token_out unless rebalancing the other way
+    pub token_in: Coin,
+    pub token_out: Coin,
+
     /// streams per day
     pub daily_streams: u64,
 
-    /// min price
-    pub min_price: f64,
+    /// max slippage from the quote taken before signing, in basis points
+    pub max_slippage_bps: Option<u64>,
+
+    /// total budget and dates, the daily amount is then a max daily rate
+    pub campaign: Option<Campaign>,
+
+    /// trading hours, trades are only attempted while the schedule is open
+    pub schedule: Option<Schedule>,
+
+    /// UTC time at which the day and its windows start
+    pub daily_reset: NaiveTime,
+
+    /// portfolio target, the stream stops once it is within the tolerance
+    pub rebalance: Option<RebalanceTarget>,
+
+    /// proceeds above a threshold are sent to a cold wallet after each trade
+    pub sweep: Option<SweepPolicy>,
 }
 
 impl Streamer {
-    pub fn new(daily_amount: u64, swap_type: &'static str, daily_streams: u64, min_price: f64) -> Self {
+    pub fn new(tiers: Vec<LadderTier>, ladder: bool, swap_type: &'static str, daily_streams: u64, max_slippage_bps: Option<u64>) -> Self {
         Streamer {
-            daily_amount,
+            tiers,
+            ladder,
+            // ladder tiers carry their unfilled allocation by default
+            catch_up: if ladder { CatchUpPolicy::NextWindow } else { CatchUpPolicy::None },
+            max_trade_amount: None,
+            size_jitter_pct: 0.0,
             swap_type,
+            token_in: CONFIG.env_constants.token_in,
+            token_out: CONFIG.env_constants.token_out,
             daily_streams,
-            min_price
+            max_slippage_bps,
+            campaign: None,
+            schedule: None,
+            daily_reset: NaiveTime::MIN,
+            rebalance: None,
+            sweep: None,
         }
     }
 
+    pub fn with_catch_up(mut self, catch_up: CatchUpPolicy, max_trade_amount: Option<u64>) -> Self {
+        self.catch_up = catch_up;
+        self.max_trade_amount = max_trade_amount;
+        self
+    }
+
+    pub fn with_size_jitter(mut self, size_jitter_pct: f64) -> Self {
+        self.size_jitter_pct = size_jitter_pct;
+        self
+    }
+
+    /// Swaps `token_in` for `token_out` until the portfolio reaches the target
+    pub fn with_rebalance(mut self, target: RebalanceTarget, token_in: Coin, token_out: Coin) -> Self {
+        self.rebalance = Some(target);
+        self.token_in = token_in;
+        self.token_out = token_out;
+        self
+    }
+
+    pub fn with_campaign(mut self, campaign: Campaign) -> Self {
+        self.campaign = Some(campaign);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: Option<Schedule>, daily_reset: NaiveTime) -> Self {
+        self.schedule = schedule;
+        self.daily_reset = daily_reset;
+        self
+    }
+
+    pub fn with_sweep(mut self, sweep: SweepPolicy) -> Self {
+        self.sweep = Some(sweep);
+        self
+    }
+
     pub async fn start(&self, signer: &Signer) {
         // Create a LocalSet to run !Send futures on the current thread
         let local = LocalSet::new();
 
-        // Clone necessary values to move into the async tasks
-        let daily_amount = self.daily_amount;
-        let swap_type = self.swap_type;
-        let daily_streams = self.daily_streams;
-        let min_price = self.min_price;
+        // Keep provider latencies and circuit breakers up to date in the background
+        let health_checks = tokio::spawn(run_health_checks());
 
         // Since we cannot clone `signer`, we need to ensure that it's used within the same scope
 
         // Start the polling service
         local.run_until(async move {
-            poll_service::start_polling(
-                signer,
-                daily_amount,
-                swap_type,
-                daily_streams,
-                min_price,
-            )
-            .await;
+            poll_service::start_polling(signer, self).await;
         })
         .await;
+
+        health_checks.abort();
     }
 }
@@ -0,0 +1,72 @@
+use std::fmt;
+use anyhow::{anyhow, bail, Result};
+use log::info;
+use crate::config::CONFIG;
+use crate::chains::coin::{Coin, CoinAmount};
+use crate::chains::osmosis::osmosis_key_service::Signer;
+use crate::chains::osmosis::osmosis_account_service::fetch_balances;
+use crate::chains::osmosis::osmosis_transfer_service::{transfer_as, Transfer};
+use crate::trade_service::available_balance;
+use crate::trade_outcome::TradeOutcome;
+
+/// Sends the stream proceeds above a retained buffer to a cold wallet
+#[derive(Debug, Clone)]
+pub struct SweepPolicy {
+    pub destination: String,
+    /// balance above which a sweep is sent, in microns
+    pub threshold: u64,
+    /// balance kept in the streaming account, in microns
+    pub buffer: u64,
+}
+
+impl SweepPolicy {
+    /// Policy of the sweep config, none when the destination or threshold is missing
+    pub fn from_config() -> Result<Option<Self>> {
+        let config = &CONFIG.sweep;
+        let (Some(destination), Some(threshold)) = (&config.destination, config.threshold) else {
+            return Ok(None);
+        };
+        if threshold < config.buffer {
+            bail!("Sweep threshold {} is below the buffer {}", threshold, config.buffer);
+        }
+
+        let policy = SweepPolicy { destination: destination.clone(), threshold, buffer: config.buffer };
+        policy.transfer(CONFIG.env_constants.token_out, 0).validate()?;
+        Ok(Some(policy))
+    }
+
+    /// Sweeps `token` when its balance exceeds the threshold, none when there is nothing to sweep
+    pub async fn sweep(&self, signer: &Signer, token: Coin) -> Result<Option<TradeOutcome>> {
+        let balances = fetch_balances(signer.get_account_address(), None).await.map_err(|e| anyhow!("Error fetching account balances: {}", e))?;
+        let balance = available_balance(&balances, token);
+        if balance <= self.threshold {
+            return Ok(None);
+        }
+
+        let transfer = self.transfer(token, balance - self.buffer);
+        info!(">>> Sweeping {} to {}", transfer.token, self.destination);
+        transfer_as(signer, &transfer, "sweep").await.map(Some)
+    }
+
+    fn transfer(&self, coin: Coin, amount: u64) -> Transfer {
+        Transfer {
+            to_address: self.destination.clone(),
+            token: CoinAmount { coin, amount },
+            memo: String::new(),
+            ibc: None,
+        }
+    }
+}
+
+impl fmt::Display for SweepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = CONFIG.env_constants.token_out;
+        write!(
+            f,
+            "above {} to {}, keeping {}",
+            CoinAmount { coin: token, amount: self.threshold },
+            self.destination,
+            CoinAmount { coin: token, amount: self.buffer },
+        )
+    }
+}
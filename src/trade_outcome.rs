@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::chains::coin::Coin;
+use crate::guard_rails::GuardRailViolation;
+
+/// Result of a single trade attempt
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TradeOutcome {
+    /// The swap was included in a block with code 0
+    Executed {
+        txhash: String,
+        tokens_in: Option<u64>,
+        tokens_out: Option<u64>,
+    },
+    /// The swap was not sent, conditions to trade were not met
+    Skipped { reason: SkipReason },
+    /// The swap was sent but did not execute
+    Failed {
+        txhash: Option<String>,
+        reason: FailureReason,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// the price could not be fetched or was not confirmed by the configured sources
+    PriceUnavailable { error: String },
+    PriceBelowMin { price: f64, min_price: f64 },
+    InsufficientBalance { coin: Coin, required: u64, available: u64 },
+    InsufficientGas { coin: Coin, required: u64, available: u64 },
+    /// a configured guard rail was crossed, the halt is recorded
+    GuardRail { violation: GuardRailViolation },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailureReason {
+    /// no provider could be reached, the tx was never sent
+    Broadcast { error: String },
+    /// rejected by CheckTx, the tx never reached a block
+    CheckTx { code: u64, raw_log: Option<String> },
+    /// included in a block with a non-zero code
+    Execution { code: u64, raw_log: Option<String> },
+    /// not found on chain before the confirmation timeout
+    Timeout,
+}
+
+/// What the stream should do after a trade attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextAction {
+    /// the window target is filled, wait for the next window
+    Done,
+    /// try again later in the same window
+    Retry,
+    /// give up on this window
+    SkipWindow,
+    /// stop the stream, retrying cannot succeed without user action
+    Halt,
+}
+
+// CheckTx codes from the cosmos-sdk that repeat on every retry
+const CODE_TX_DECODE: u64 = 2;
+const CODE_UNAUTHORIZED: u64 = 4;
+const CODE_INSUFFICIENT_FEE: u64 = 13;
+
+impl TradeOutcome {
+    pub fn next_action(&self) -> NextAction {
+        match self {
+            TradeOutcome::Executed { .. } => NextAction::Done,
+            TradeOutcome::Skipped { reason } => match reason {
+                SkipReason::PriceUnavailable { .. } | SkipReason::PriceBelowMin { .. } => NextAction::Retry,
+                SkipReason::InsufficientBalance { .. } | SkipReason::InsufficientGas { .. } => NextAction::SkipWindow,
+                SkipReason::GuardRail { .. } => NextAction::Halt,
+            },
+            TradeOutcome::Failed { reason, .. } => match reason {
+                FailureReason::CheckTx { code, .. }
+                    if [CODE_TX_DECODE, CODE_UNAUTHORIZED, CODE_INSUFFICIENT_FEE].contains(code) => NextAction::Halt,
+                // nothing reached the chain, or the tx is final and did not execute
+                FailureReason::Broadcast { .. } | FailureReason::CheckTx { .. } | FailureReason::Execution { .. } => NextAction::Retry,
+                // the tx may still land, retrying could trade twice. Broadcasts that
+                // failed after reaching a provider end here too
+                FailureReason::Timeout => NextAction::SkipWindow,
+            },
+        }
+    }
+
+    pub fn txhash(&self) -> Option<&str> {
+        match self {
+            TradeOutcome::Executed { txhash, .. } => Some(txhash),
+            TradeOutcome::Failed { txhash, .. } => txhash.as_deref(),
+            TradeOutcome::Skipped { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TradeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradeOutcome::Executed { txhash, tokens_in, tokens_out } => {
+                write!(f, "executed {} (in: {:?}, out: {:?})", txhash, tokens_in, tokens_out)
+            }
+            TradeOutcome::Skipped { reason } => match reason {
+                SkipReason::PriceUnavailable { error } => write!(f, "skipped, price unavailable: {}", error),
+                SkipReason::PriceBelowMin { price, min_price } => write!(f, "skipped, price {} is below min price {}", price, min_price),
+                SkipReason::InsufficientBalance { coin, required, available } => write!(f, "skipped, insufficient {} balance: {} required, {} available", coin, required, available),
+                SkipReason::InsufficientGas { coin, required, available } => write!(f, "skipped, insufficient {} for gas: {} required, {} available", coin, required, available),
+                SkipReason::GuardRail { violation } => write!(f, "halted by guard rail, {}", violation),
+            },
+            TradeOutcome::Failed { txhash, reason } => {
+                let txhash = txhash.as_deref().unwrap_or("-");
+                match reason {
+                    FailureReason::Broadcast { error } => write!(f, "failed to broadcast: {}", error),
+                    FailureReason::CheckTx { code, raw_log } => write!(f, "{} rejected by CheckTx with code {}: {}", txhash, code, raw_log.as_deref().unwrap_or("")),
+                    FailureReason::Execution { code, raw_log } => write!(f, "{} failed with code {}: {}", txhash, code, raw_log.as_deref().unwrap_or("")),
+                    FailureReason::Timeout => write!(f, "{} not confirmed before timeout", txhash),
+                }
+            }
+        }
+    }
+}
@@ -2,11 +2,18 @@ use log::{error, info, warn};
 use crate::config::CONFIG;
 use crate::chains::coin::Coin;
 use crate::chains::osmosis::osmosis_pool_service;
+use crate::chains::osmosis::osmosis_pool_service::SwapOrder;
 use crate::chains::osmosis::osmosis_key_service::Signer;
-use crate::chains::osmosis::osmosis_pool_service::fetch_coin_price;
+use crate::chains::osmosis::osmosis_transaction::record_unsent_outcome;
+use crate::chains::osmosis::osmosis_tx_builder::FeeStrategy;
+use crate::price_service::fetch_validated_price;
 use crate::chains::osmosis::osmosis_account_service::fetch_balances;
 use crate::chains::coin::CoinAmount;
+use crate::trade_outcome::{NextAction, SkipReason, TradeOutcome};
+use crate::gas_station;
+use crate::guard_rails::{self, GuardRailViolation};
 use anyhow::{anyhow, Result};
+use serde_json::json;
 
 /// the trade tasks that the stream processes
 pub struct TradeTask {
@@ -16,6 +23,11 @@ pub struct TradeTask {
     amount: u64,
     swap_type: &'static str,
     min_price: f64,
+    max_slippage_bps: Option<u64>,
+    /// min price of the ladder tier this task fills
+    ladder_tier: Option<f64>,
+    /// the task moves the portfolio towards its rebalance target
+    rebalance: bool,
 }
 
 impl TradeTask {
@@ -26,6 +38,7 @@ impl TradeTask {
         amount: u64,
         swap_type: &'static str,
         min_price: f64,
+        max_slippage_bps: Option<u64>,
     ) -> Self {
         TradeTask {
             pool_id,
@@ -34,85 +47,166 @@ impl TradeTask {
             amount,
             swap_type,
             min_price,
+            max_slippage_bps,
+            ladder_tier: None,
+            rebalance: false,
         }
     }
+
+    /// Records the trade as a fill of the given ladder tier
+    pub fn with_ladder_tier(mut self, min_price: f64) -> Self {
+        self.ladder_tier = Some(min_price);
+        self
+    }
+
+    /// Records the trade as a rebalance swap
+    pub fn with_rebalance(mut self) -> Self {
+        self.rebalance = true;
+        self
+    }
 }
 
 impl TradeTask {
-    pub async fn execute(&self, signer: &Signer) -> Result<bool, anyhow::Error> {
+    pub async fn execute(&self, signer: &Signer) -> Result<TradeOutcome, anyhow::Error> {
+        let outcome = self.attempt(signer).await?;
+
+        // A skipped trade is never broadcasted, its outcome is stored apart from the ledger
+        if let TradeOutcome::Skipped { .. } = outcome {
+            let entry = json!({
+                "pool_id": self.pool_id,
+                "token_in": self.token_in,
+                "token_out": self.token_out,
+                "amount": self.amount,
+                "swap_type": self.swap_type,
+                "min_price": self.min_price,
+            });
+            if let Err(e) = record_unsent_outcome(signer.get_account_address(), &entry, &outcome) {
+                error!("!!! Error recording trade outcome: {:?}", e);
+            }
+        }
+        Ok(outcome)
+    }
+
+    async fn attempt(&self, signer: &Signer) -> Result<TradeOutcome, anyhow::Error> {
         // Note: some checks can be removed to run faster
-        
-        // 1. Check coin price
-        let price = match fetch_coin_price(self.pool_id).await {
+
+        // 0. Check the guard rails on fees and realized price
+        if let Some(violation) = guard_rails::check_ledger(signer.get_account_address(), self.pool_id)? {
+            return Ok(halt(signer, violation));
+        }
+
+        // 1. Check coin price, cross-checked against the configured sources
+        let (token_in, token_out) = (CONFIG.env_constants.token_in, CONFIG.env_constants.token_out);
+        let price = match fetch_validated_price(self.pool_id, token_in, token_out).await {
+            // Pool prices are quoted for the configured token_in, invert them for swaps the other way
+            Ok(value) if self.token_in == token_out => {
+                1.0 / value
+            }
             Ok(value) => {
                 value
             }
             Err(e) => {
-                error!("!!! 1. Error fetching coin price: {:?}", e);
-                return Ok(false);
+                error!("!!! 1. Error validating coin price: {:?}", e);
+                return Ok(TradeOutcome::Skipped {
+                    reason: SkipReason::PriceUnavailable { error: e.to_string() },
+                });
             }
         };
         if price < self.min_price {
             warn!("!!! 1. Current price {} is less than min price {} to perform swap", price, self.min_price);
-            return Ok(false);
+            return Ok(TradeOutcome::Skipped {
+                reason: SkipReason::PriceBelowMin { price, min_price: self.min_price },
+            });
+        }
+        if let Some(violation) = guard_rails::check_price(self.token_out, price) {
+            return Ok(halt(signer, violation));
         }
         info!(">>> 1. Current price {} is above min price {}", price, self.min_price);
 
         // Fetch account balances
-        let balances = match fetch_balances(signer.get_account_address(), None).await {
-            Ok(balances) => balances,
-            Err(e) => {
-                error!("!!! 2. Error fetching account balances: {:?}", e);
-                return Ok(false);
+        let mut balances = fetch_balances(signer.get_account_address(), None)
+            .await
+            .map_err(|e| anyhow!("!!! 2. Error fetching account balances: {}", e))?;
+
+        // 2. Top up the gas token from the gas station when it runs low
+        let gas_token = CONFIG.gas_config.token;
+        match gas_station::top_up_if_needed(signer, available_balance(&balances, gas_token)).await {
+            Ok(Some(outcome)) if outcome.next_action() == NextAction::Done => {
+                info!(">>> 2. Gas token topped up");
+                balances = fetch_balances(signer.get_account_address(), None)
+                    .await
+                    .map_err(|e| anyhow!("!!! 2. Error fetching account balances: {}", e))?;
             }
-        };
+            Ok(Some(outcome)) => warn!("!!! 2. Gas top up not executed: {}", outcome),
+            Ok(None) => {}
+            Err(e) => warn!("!!! 2. Error topping up gas: {:?}", e),
+        }
 
-        // 2. Check account balance for the token to swap
+        // 3. Check account balance for the token to swap, the amount is in token_in
+        // for amount_in swaps and in token_out for amount_out swaps
         let trade_amount = match self.swap_type {
             "amount_out" => (self.amount as f64 / price) as u64,
-            "amount_in" => (self.amount as f64 * price) as u64,
-            _ => {
-                error!("!!! 2. Invalid swap type: {}", self.swap_type);
-                return Ok(false);
-            }
+            "amount_in" => self.amount,
+            _ => return Err(anyhow!("!!! 3. Invalid swap type: {}", self.swap_type)),
         };
         
-        if let Err(e) = has_sufficient_balance(&balances, self.token_in, trade_amount) {
-            error!("{}", e);
-            return Ok(false);
+        let available = available_balance(&balances, self.token_in);
+        if available < trade_amount {
+            error!("!!! 3. Insufficient balance for token: {}", self.token_in);
+            return Ok(TradeOutcome::Skipped {
+                reason: SkipReason::InsufficientBalance { coin: self.token_in, required: trade_amount, available },
+            });
+        }
+        if let Some(violation) = guard_rails::check_balance(self.token_in, available, trade_amount) {
+            return Ok(halt(signer, violation));
         }
-        info!(">>> 2. Account has enough balance to perform swap");
+        info!(">>> 3. Account has enough balance to perform swap");
 
-        // 3. Ensure account has enough balance to pay for fees
-        // TODO: Implement gas station
-        if let Err(e) = has_sufficient_balance(&balances, CONFIG.gas_config.token, CONFIG.gas_config.gas_limit) {
-            error!("{}", e);
-            return Ok(false);
+        // 4. Ensure account has enough balance to pay for fees, swaps pay a jittered fee
+        let gas_available = available_balance(&balances, gas_token);
+        let max_fee = FeeStrategy::Jittered.max_amount();
+        if gas_available < max_fee {
+            error!("!!! 4. Insufficient balance for token: {}", gas_token);
+            return Ok(TradeOutcome::Skipped {
+                reason: SkipReason::InsufficientGas { coin: gas_token, required: max_fee, available: gas_available },
+            });
         }
-        info!(">>> 3. Account has enough gas balance to cover fees");
+        info!(">>> 4. Account has enough gas balance to cover fees");
      
-        // 4. Perform the swap
-        osmosis_pool_service::perform_swap(
-            signer,
-            self.pool_id,
-            self.token_in,
-            self.token_out,
-            self.amount,
-            self.swap_type,
-            self.min_price,
-        ).await
+        // 5. Perform the swap
+        let order = SwapOrder {
+            pool_id: self.pool_id,
+            coin_in: self.token_in,
+            coin_out: self.token_out,
+            amount: self.amount,
+            swap_type: self.swap_type,
+            min_price: self.min_price,
+            max_slippage_bps: self.max_slippage_bps,
+            ladder_tier: self.ladder_tier,
+            market_side: None,
+            rebalance: self.rebalance,
+            action: None,
+        };
+        osmosis_pool_service::perform_swap(signer, &order).await
     }
 }
 
-// Helper function
-fn has_sufficient_balance(balances: &[CoinAmount], token: Coin, required_amount: u64) -> Result<(), anyhow::Error> {
-    let denom = token.denom();
-    if let Some(balance) = balances.iter().find(|b| b.coin.denom() == denom) {
-        if balance.amount < required_amount {
-            return Err(anyhow!("Insufficient balance for token: {}", denom));
-        }
-    } else {
-        return Err(anyhow!("No balance found for token: {}", denom));
+// Helper function, records the halt so the stream cannot restart until resumed
+fn halt(signer: &Signer, violation: GuardRailViolation) -> TradeOutcome {
+    error!("!!! Guard rail triggered: {}", violation);
+    if let Err(e) = guard_rails::record_halt(signer.get_account_address(), &violation) {
+        error!("!!! Error recording halt: {:?}", e);
+    }
+    TradeOutcome::Skipped {
+        reason: SkipReason::GuardRail { violation },
     }
-    Ok(())
+}
+
+/// Balance of a token, 0 when the account holds none of it
+pub fn available_balance(balances: &[CoinAmount], token: Coin) -> u64 {
+    let denom = token.denom();
+    balances
+        .iter()
+        .find(|b| b.coin.denom() == denom)
+        .map_or(0, |balance| balance.amount)
 }